@@ -9,9 +9,18 @@ pub const VERSION: &'static str = "0.0.1";
 
 mod spine;
 
+pub use spine::book;
+pub use spine::endgame;
+pub use spine::mate;
 pub use spine::movegen;
+pub use spine::movepick;
+pub use spine::nnue;
 pub use spine::perft;
+pub use spine::pgn;
 pub use spine::piece_attacks;
+pub use spine::retro;
+pub use spine::timeman;
+pub use spine::tournament;
 
 pub mod flags;
 pub mod macros;
@@ -23,9 +32,12 @@ pub mod prelude {
     pub use spine::File;
     pub use spine::Movelist;
     pub use spine::Rank;
+    pub use spine::Score;
     pub use spine::Square;
     pub use spine::{Bitboard, ShiftDir};
-    pub use spine::{Board, CastleRights, State};
+    pub use spine::{Board, BoardBuilder, BoardCreationError, PositionError};
+    pub use spine::{CastleRight, CastleRights, State};
+    pub use spine::{FenError, FenErrorKind};
     pub use spine::{Move, MoveFlag};
     pub use spine::{Piece, PieceType};
 }
@@ -1,12 +1,38 @@
+use chess::book::{BookBuilder, Random64};
+use chess::mate;
 use chess::perft;
+use chess::pgn::PgnReader;
+use chess::retro::Table;
+use chess::tournament::{self, Hypothesis, Opening, Player, Sprt, Stats, TimeControl, UciEngine};
 
 use chess::move_new;
 use chess::prelude::*;
 
-fn main() {
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+fn main() -> ExitCode {
     chess::initialize_bitboards();
     //chess::print_comp_flags();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("makebook") => make_book(&args[1..]),
+        Some("retro") => retro(&args[1..]),
+        Some("match") => play_match(&args[1..]),
+        Some("bench") => bench(&args[1..]),
+        Some("mate") => solve_mate(&args[1..]),
+        _ => {
+            scratch();
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+fn scratch() {
     let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -";
     let mut s = State::new();
     let mut b = Board::new(fen, &mut s).unwrap();
@@ -23,3 +49,474 @@ fn main() {
     let u = perft::perft_on(&mut b, &mut s, 5 - moves.len());
     println!("Nodes searched: {u}");
 }
+
+const BENCH_USAGE: &str = "usage: chess bench [DEPTH] [THREADS]";
+
+/// `bench`: count the nodes of a fixed set of positions, to check that move
+/// generation is unchanged and to time it
+fn bench(args: &[String]) -> ExitCode {
+    let mut numbers = args
+        .iter()
+        .map(|a| a.parse::<usize>().ok().filter(|&n| n > 0));
+    let depth = numbers.next().unwrap_or(Some(perft::BENCH_DEPTH));
+    let threads = numbers.next().unwrap_or(Some(1));
+    let (Some(depth), Some(threads), None) = (depth, threads, numbers.next()) else {
+        eprintln!("{BENCH_USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let start = Instant::now();
+    let nodes = perft::bench(depth, threads);
+    let elapsed = start.elapsed();
+
+    println!("Total time (ms) : {}", elapsed.as_millis());
+    println!("Nodes searched  : {nodes}");
+    println!(
+        "Nodes/second    : {}",
+        (nodes as f64 / elapsed.as_secs_f64().max(1e-9)) as u64
+    );
+    ExitCode::SUCCESS
+}
+
+const MATE_USAGE: &str = "usage: chess mate [--checks] N FEN";
+
+/// `mate`: check a "mate in N" problem for cooks and duals
+fn solve_mate(args: &[String]) -> ExitCode {
+    let checks_only = args.first().is_some_and(|a| a == "--checks");
+    let args = &args[checks_only as usize..];
+
+    let Some(moves) = args.first().and_then(|n| n.parse().ok()).filter(|&n| n > 0) else {
+        eprintln!("{MATE_USAGE}");
+        return ExitCode::FAILURE;
+    };
+    // The FEN may come as one argument or as one per field
+    let fen = args[1..].join(" ");
+
+    let mut state = State::new();
+    let board = match Board::new(fen.as_str(), &mut state) {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("bad position: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let solution = mate::solve(&board, &state, moves, checks_only);
+    if !solution.is_proven() {
+        println!("no mate in {moves}");
+        return ExitCode::FAILURE;
+    }
+
+    print!("{}", solution.to_text(&board, &state));
+    println!();
+    match solution.cooks().len() {
+        0 => println!("sound: one key"),
+        n => println!("cooked: {} keys", n + 1),
+    }
+    for dual in solution.duals() {
+        let line: Vec<String> = dual.iter().map(Move::to_string).collect();
+        println!("dual after {}", line.join(" "));
+    }
+
+    ExitCode::SUCCESS
+}
+
+const MAKEBOOK_USAGE: &str = "usage: chess makebook [--random FILE] [--max-ply N] \
+                              [--min-count N] -o OUT.bin GAMES.pgn...";
+
+/// `makebook`: build a Polyglot book out of PGN files
+fn make_book(args: &[String]) -> ExitCode {
+    let mut random = None;
+    let mut max_ply = None;
+    let mut min_count = None;
+    let mut out = None;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned();
+        let ok = match arg.as_str() {
+            "--random" => value().map(|v| random = Some(v)).is_some(),
+            "--max-ply" => value()
+                .and_then(|v| v.parse().ok())
+                .map(|v| max_ply = Some(v))
+                .is_some(),
+            "--min-count" => value()
+                .and_then(|v| v.parse().ok())
+                .map(|v| min_count = Some(v))
+                .is_some(),
+            "-o" => value().map(|v| out = Some(v)).is_some(),
+            a if a.starts_with('-') => false,
+            a => {
+                inputs.push(a.to_string());
+                true
+            }
+        };
+
+        if !ok {
+            eprintln!("{MAKEBOOK_USAGE}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let Some(out) = out else {
+        eprintln!("{MAKEBOOK_USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let random = match random {
+        Some(path) => match Random64::load(&path) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("could not read {path}: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Random64::polyglot(),
+    };
+
+    let mut builder = BookBuilder::new(random);
+    if let Some(n) = max_ply {
+        builder.max_ply(n);
+    }
+    if let Some(n) = min_count {
+        builder.min_count(n);
+    }
+
+    for path in inputs {
+        let file = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("could not open {path}: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let (mut added, mut skipped) = (0, 0);
+        for (i, game) in PgnReader::new(BufReader::new(file)).enumerate() {
+            match game.and_then(|g| builder.add_game(&g)) {
+                Ok(()) => added += 1,
+                Err(e) => {
+                    eprintln!("{path}: game {}: {e}", i + 1);
+                    skipped += 1;
+                }
+            }
+        }
+        eprintln!("{path}: {added} games added, {skipped} skipped");
+    }
+
+    let written = File::create(&out).and_then(|f| builder.write(BufWriter::new(f)));
+    match written {
+        Ok(n) => {
+            eprintln!("wrote {n} entries to {out}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("could not write {out}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+const RETRO_USAGE: &str = "usage: chess retro [-o DIR] MATERIAL...";
+
+/// `retro`: generate depth to mate tables, and those they depend on
+fn retro(args: &[String]) -> ExitCode {
+    let mut dir = PathBuf::from(".");
+    let mut materials = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(d) => dir = PathBuf::from(d),
+                None => {
+                    eprintln!("{RETRO_USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            m => materials.push(m.to_string()),
+        }
+    }
+
+    if materials.is_empty() {
+        eprintln!("{RETRO_USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    for material in materials {
+        let Some(tables) = Table::generate_all(&material) else {
+            eprintln!("{material}: need one king each and at most four pieces");
+            return ExitCode::FAILURE;
+        };
+
+        for table in tables {
+            let path = dir.join(format!("{}.dtm", table.signature()));
+            if let Err(e) = table.save(&path) {
+                eprintln!("could not write {}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+
+            match table.longest_win() {
+                Some(s) => eprintln!("{}: longest win {s}", path.display()),
+                None => eprintln!("{}: no wins", path.display()),
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+const MATCH_USAGE: &str =
+    "usage: chess match --engine cmd=PATH[,name=NAME][,arg=ARG][,option.NAME=VALUE] \
+                           --engine ... [--openings FILE.epd|FILE.pgn] [--tc [MOVES/]SECS[+INC]] \
+                           [--rounds N] [--pgn OUT.pgn] [--sprt ELO0 ELO1] [--alpha A] [--beta B]";
+
+/// How to start one engine of a match
+struct EngineSpec {
+    command: String,
+    name: Option<String>,
+    args: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl EngineSpec {
+    /// Read `cmd=PATH,name=NAME,arg=ARG,option.NAME=VALUE`, where only `cmd`
+    /// is needed and `arg` and `option` may be repeated
+    fn parse(s: &str) -> Option<Self> {
+        let mut spec = Self {
+            command: String::new(),
+            name: None,
+            args: Vec::new(),
+            options: Vec::new(),
+        };
+        for part in s.split(',') {
+            let (key, value) = part.split_once('=')?;
+            match key {
+                "cmd" => spec.command = value.to_string(),
+                "name" => spec.name = Some(value.to_string()),
+                "arg" => spec.args.push(value.to_string()),
+                _ => {
+                    let name = key.strip_prefix("option.")?;
+                    spec.options.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+        (!spec.command.is_empty()).then_some(spec)
+    }
+
+    fn start(&self) -> std::io::Result<UciEngine> {
+        let mut engine = UciEngine::start(&self.command, &self.args, &self.options)?;
+        if let Some(name) = &self.name {
+            engine.set_name(name);
+        }
+        Ok(engine)
+    }
+}
+
+/// Every opening in an EPD file, or in a PGN file if the name doesn't end in
+/// `.epd`
+fn read_openings(path: &str) -> Result<Vec<Opening>, String> {
+    let file = File::open(path).map_err(|e| format!("could not open {path}: {e}"))?;
+    let reader = BufReader::new(file);
+
+    let mut openings = Vec::new();
+    if path.ends_with(".epd") {
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("could not read {path}: {e}"))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let opening = Opening::from_epd(&line)
+                .ok_or_else(|| format!("{path}: line {}: not a valid position", i + 1))?;
+            openings.push(opening);
+        }
+    } else {
+        for (i, game) in PgnReader::new(reader).enumerate() {
+            let opening = game
+                .and_then(|g| Opening::from_pgn(&g))
+                .map_err(|e| format!("{path}: game {}: {e}", i + 1))?;
+            openings.push(opening);
+        }
+    }
+
+    if openings.is_empty() {
+        return Err(format!("{path}: no openings"));
+    }
+    Ok(openings)
+}
+
+/// A number strictly between zero and one, as an SPRT error rate has to be
+fn probability(s: &str) -> Option<f64> {
+    s.parse().ok().filter(|p| *p > 0.0 && *p < 1.0)
+}
+
+/// `match`: play two engines against each other and report on the results
+fn play_match(args: &[String]) -> ExitCode {
+    let mut engines = Vec::new();
+    let mut openings = None;
+    let mut time_control = TimeControl::parse("10+0.1");
+    let mut rounds = Some(100);
+    let mut pgn_out = None;
+    let mut sprt = None;
+    let mut alpha = Some(0.05);
+    let mut beta = Some(0.05);
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned();
+        let ok = match arg.as_str() {
+            "--engine" => value()
+                .and_then(|v| EngineSpec::parse(&v))
+                .map(|e| engines.push(e))
+                .is_some(),
+            "--openings" => value().map(|v| openings = Some(v)).is_some(),
+            "--tc" => {
+                time_control = value().and_then(|v| TimeControl::parse(&v));
+                time_control.is_some()
+            }
+            "--rounds" => {
+                rounds = value().and_then(|v| v.parse().ok()).filter(|&n| n > 0);
+                rounds.is_some()
+            }
+            "--pgn" => value().map(|v| pgn_out = Some(v)).is_some(),
+            "--sprt" => {
+                let elo0 = value().and_then(|v| v.parse().ok());
+                let elo1 = value().and_then(|v| v.parse().ok());
+                sprt = elo0.zip(elo1);
+                sprt.is_some()
+            }
+            "--alpha" => {
+                alpha = value().and_then(|v| probability(&v));
+                alpha.is_some()
+            }
+            "--beta" => {
+                beta = value().and_then(|v| probability(&v));
+                beta.is_some()
+            }
+            _ => false,
+        };
+
+        if !ok {
+            eprintln!("{MATCH_USAGE}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let (Some(time_control), Some(rounds), Some(alpha), Some(beta)) =
+        (time_control, rounds, alpha, beta)
+    else {
+        unreachable!("bad values are caught while parsing")
+    };
+    if engines.len() != 2 {
+        eprintln!("{MATCH_USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let sprt = sprt.map(|(elo0, elo1)| Sprt {
+        elo0,
+        elo1,
+        alpha,
+        beta,
+    });
+
+    let openings = match openings {
+        Some(path) => match read_openings(&path) {
+            Ok(o) => o,
+            Err(e) => {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => vec![Opening::default()],
+    };
+
+    let mut players = Vec::new();
+    for spec in &engines {
+        match spec.start() {
+            Ok(p) => players.push(p),
+            Err(e) => {
+                eprintln!("could not start {}: {e}", spec.command);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let [first, second] = players.as_mut_slice() else {
+        unreachable!()
+    };
+
+    let mut pgn = match pgn_out.as_ref().map(File::create).transpose() {
+        Ok(f) => f.map(BufWriter::new),
+        Err(e) => {
+            eprintln!("could not write {}: {e}", pgn_out.unwrap());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    eprintln!("{} vs {}", first.name(), second.name());
+    let mut stats = Stats::default();
+    let mut game_number = 0;
+    'rounds: for round in 0..rounds {
+        let opening = &openings[round % openings.len()];
+
+        // Each opening is played with both colours, so neither engine gets
+        // the better side of it
+        for first_color in [Color::White, Color::Black] {
+            let game = match first_color {
+                Color::White => tournament::play_game(first, second, opening, &time_control),
+                Color::Black => tournament::play_game(second, first, opening, &time_control),
+            };
+            game_number += 1;
+            stats.add(game.outcome, first_color);
+
+            if let Some(out) = &mut pgn {
+                let written = out
+                    .write_all(game.to_pgn(game_number, &time_control).as_bytes())
+                    .and_then(|_| out.flush());
+                if let Err(e) = written {
+                    eprintln!("could not write {}: {e}", pgn_out.as_ref().unwrap());
+                    return ExitCode::FAILURE;
+                }
+            }
+
+            let mut report = format!(
+                "game {game_number}: {} ({}) | +{} ={} -{}",
+                game.outcome.to_pgn(),
+                game.termination,
+                stats.wins,
+                stats.draws,
+                stats.losses
+            );
+            if let Some((elo, margin)) = stats.elo() {
+                report += &format!(" | elo {elo:.1} +/- {margin:.1}");
+            }
+            if let Some(sprt) = &sprt {
+                let (lower, upper) = sprt.bounds();
+                report += &format!(" | llr {:.2} ({lower:.2}, {upper:.2})", sprt.llr(&stats));
+            }
+            eprintln!("{report}");
+
+            if let Some(verdict) = sprt.and_then(|s| s.verdict(&stats)) {
+                match verdict {
+                    Hypothesis::H0 => println!("H0 accepted: the change is not an improvement"),
+                    Hypothesis::H1 => println!("H1 accepted: the change is an improvement"),
+                }
+                break 'rounds;
+            }
+        }
+    }
+
+    println!(
+        "{} vs {}: +{} ={} -{} ({:.1}%)",
+        first.name(),
+        second.name(),
+        stats.wins,
+        stats.draws,
+        stats.losses,
+        stats.score() * 100.0
+    );
+    if let Some((elo, margin)) = stats.elo() {
+        println!("elo difference: {elo:.1} +/- {margin:.1}");
+    }
+
+    ExitCode::SUCCESS
+}
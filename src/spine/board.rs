@@ -1,10 +1,15 @@
 use crate::piece_attacks;
 use crate::prelude::*;
+use crate::spine::fen::{self, FenErrorKind};
 
 use std::fmt;
 use std::mem::transmute;
 use std::ptr::NonNull;
 
+mod builder;
+
+pub use builder::BoardBuilder;
+
 macro_rules! ret_false_if {
     ($cond:expr) => {
         if $cond {
@@ -46,10 +51,20 @@ pub struct State {
     blockers: [Bitboard; Color::COUNT],
     pinners: [Bitboard; Color::COUNT],
     captured_piece: Option<PieceType>,
+    dirty: [Option<DirtyPiece>; 3],
+    attacks: [Option<[Bitboard; PieceType::COUNT]>; Color::COUNT],
 
     prev: Option<NonNull<Self>>,
 }
 
+/// A piece that the last move put on, took off or moved across the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct DirtyPiece {
+    pub(crate) piece: Piece,
+    pub(crate) from: Option<Square>,
+    pub(crate) to: Option<Square>,
+}
+
 impl Board {
     pub const STARTPOS: &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
     pub const KIWIPETE: &'static str =
@@ -123,6 +138,67 @@ impl Board {
         rooks | bishops
     }
 
+    /// Whether any piece of `by` attacks `square`
+    pub fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+        (self.attacks_to(square) & self.color(by)).gtz()
+    }
+
+    /// The squares attacked by the pieces of type `pt` of `color`, which is
+    /// worked out once per position and kept in `s`
+    pub fn attacks_by_piece(&self, s: &mut State, color: Color, pt: PieceType) -> Bitboard {
+        self.attack_map(s, color)[pt.to_usize()]
+    }
+
+    /// The squares attacked by any piece of `color`
+    pub fn attacks_by(&self, s: &mut State, color: Color) -> Bitboard {
+        self.attack_map(s, color)
+            .into_iter()
+            .fold(Bitboard::ZERO, |acc, bb| acc | bb)
+    }
+
+    /// The pieces of `color`, other than the king, that are attacked and not
+    /// defended
+    pub fn hanging_pieces(&self, s: &mut State, color: Color) -> Bitboard {
+        let attacked = self.attacks_by(s, !color);
+        let defended = self.attacks_by(s, color);
+        (self.color(color) ^ self.king(color)) & attacked & !defended
+    }
+
+    /// The sliders of `color` that attack `square` from behind a piece which
+    /// attacks it directly, such as a rook backing up a queen on the same file.
+    ///
+    /// Only one piece deep: a third piece in the battery is not included.
+    pub fn x_ray_attacks(&self, square: Square, color: Color) -> Bitboard {
+        let direct = self.attacks_to(square);
+        self.sliders_to(square, self.all() ^ direct) & self.color(color) & !direct
+    }
+
+    fn attack_map(&self, s: &mut State, color: Color) -> [Bitboard; PieceType::COUNT] {
+        use PieceType::*;
+
+        if let Some(map) = s.attacks[color.to_usize()] {
+            return map;
+        }
+
+        let occupied = self.all();
+        let each = |pt: PieceType, f: fn(Square, Bitboard) -> Bitboard| {
+            self.spec(color, pt)
+                .into_iter()
+                .fold(Bitboard::ZERO, |acc, sq| acc | f(sq, occupied))
+        };
+
+        let map = [
+            piece_attacks::pawn_attacks_by_board(self.spec(color, Pawn), color),
+            piece_attacks::knight_attacks_by_board(self.spec(color, Knight)),
+            each(Bishop, piece_attacks::bishop_attacks),
+            each(Rook, piece_attacks::rook_attacks),
+            each(Queen, piece_attacks::queen_attacks),
+            piece_attacks::king_attacks(self.king(color)),
+        ];
+        s.attacks[color.to_usize()] = Some(map);
+        map
+    }
+
     fn str_history(&self) -> String {
         self.history
             .iter()
@@ -303,9 +379,8 @@ impl Board {
         let us = self.to_move();
         let them = !us;
 
-        debug_assert_eq!(self.attacks_to(self.king(them)) & self.color(us), Z);
-
         s.checkers = Z;
+        s.attacks = [None; Color::COUNT];
         s.blockers[us.to_usize()] = Z;
         s.blockers[them.to_usize()] = Z;
         s.check_squares[PieceType::King.to_usize()] = Z;
@@ -363,6 +438,7 @@ impl Board {
         self.ply += 1;
         s.half_moves += 1;
         s.plies_from_null += 1;
+        s.dirty = [None; 3];
 
         let f = mv.from_square();
         let t = mv.to_square();
@@ -392,6 +468,7 @@ impl Board {
             };
 
             _ = self.remove_piece(cap_square).map(|x| x.kind());
+            s.push_dirty(pc + them, Some(cap_square), None);
 
             if pc == PieceType::Rook {
                 s.castle_rights.mut_rights_for(them).map(|right| {
@@ -413,6 +490,12 @@ impl Board {
         } else {
             let _ = self.remove_piece(f);
             self.add_piece(t, mov);
+            if flag == MoveFlag::Promotion {
+                s.push_dirty(mov, Some(f), None);
+                s.push_dirty(promt + us, None, Some(t));
+            } else {
+                s.push_dirty(mov, Some(f), Some(t));
+            }
         }
 
         // OPT: Is this faster just to skip the check?
@@ -458,6 +541,10 @@ impl Board {
         self.to_move = them;
         self.history.push(mv);
         self.compute_state(s);
+        debug_assert_eq!(
+            self.attacks_to(self.king(us)) & self.color(them),
+            Bitboard::ZERO
+        );
 
         Ok(())
     }
@@ -524,10 +611,46 @@ impl Board {
         self.ply -= 1;
     }
 
+    /// Pass the turn to the other side without moving, as null-move pruning
+    /// does. Returns [`Move::NULL`] as an error, changing nothing, if the side
+    /// to move is in check.
+    pub fn do_null_move(&mut self, s: &mut State) -> Result<(), Move> {
+        if s.checkers().gtz() {
+            return Err(Move::NULL);
+        }
+
+        unsafe {
+            *s = s.make_own_child();
+        }
+
+        self.ply += 1;
+        s.half_moves += 1;
+        s.plies_from_null = 0;
+        s.en_passant = None;
+        s.captured_piece = None;
+        s.dirty = [None; 3];
+
+        self.to_move = !self.to_move;
+        self.history.push(Move::NULL);
+        self.compute_state(s);
+        Ok(())
+    }
+
+    pub fn undo_null_move(&mut self, s: &mut State) {
+        let prev_mov = self.history.pop();
+        debug_assert_eq!(prev_mov, Some(Move::NULL));
+
+        self.to_move = !self.to_move;
+        unsafe { *s = *s.collapse().unwrap() };
+        self.ply -= 1;
+    }
+
     pub fn add_piece(&mut self, s: Square, p: Piece) {
+        debug_assert!(self.get_piece(s).is_none());
         self.color_bb[p.color().to_usize()] |= s;
         self.piece_bb[p.kind().to_usize()] |= s;
         self.pieces[s.to_usize()] = Some(p);
+        self.piece_count[PieceType::COUNT * p.color().to_usize() + p.kind().to_usize()] += 1;
     }
     pub fn remove_piece(&mut self, s: Square) -> Option<Piece> {
         let popt = self.get_piece(s);
@@ -535,6 +658,7 @@ impl Board {
             self.color_bb[p.color().to_usize()] ^= s;
             self.piece_bb[p.kind().to_usize()] ^= s;
             self.pieces[s.to_usize()] = None;
+            self.piece_count[PieceType::COUNT * p.color().to_usize() + p.kind().to_usize()] -= 1;
         }
 
         popt
@@ -569,210 +693,186 @@ impl Board {
 
         self.add_piece(kto, k);
         self.add_piece(rook_t, r);
+
+        if APPLY {
+            s.push_dirty(k, Some(kfr), Some(kto));
+            s.push_dirty(r, Some(rook_r), Some(rook_t));
+        }
     }
 
     /// Create a new [`Board`] and set up a proper [`State`] for
     /// the board given some [FEN](https://en.wikipedia.org/wiki/Forsyth-Edwards_Notation)
+    ///
+    /// The position must pass [`Board::validate`].
     pub fn new<S>(fen: S, state: &mut State) -> Result<Self, BoardCreationError>
     where
         S: Into<String>,
     {
-        let mut b = Self {
-            color_bb: [Bitboard::ZERO; Color::COUNT],
-            piece_bb: [Bitboard::ZERO; PieceType::COUNT],
-            pieces: [None; Square::COUNT],
-            piece_count: [0; PieceType::COUNT * Color::COUNT],
-            to_move: Color::White,
-            ply: 0,
+        Self::from_fen(fen.into(), state, true)
+    }
 
-            history: Vec::with_capacity(2usize.pow(11)),
-            is960: false,
-        };
+    /// Like [`Board::new`], but accepts positions that could not arise in a game
+    /// (extra queens, pawns on the back rank, ...) as long as the move
+    /// generator can still make sense of them.
+    pub fn new_lenient<S>(fen: S, state: &mut State) -> Result<Self, BoardCreationError>
+    where
+        S: Into<String>,
+    {
+        Self::from_fen(fen.into(), state, false)
+    }
 
-        let fen: String = fen.into();
-        let mut chars = fen.chars();
+    fn from_fen(fen: String, state: &mut State, strict: bool) -> Result<Self, BoardCreationError> {
+        use FenErrorKind::*;
 
-        if fen.len() == 0 {
-            return Err(BoardCreationError::NoFenGiven);
-        }
+        let mut b = Self::empty();
 
-        let mut ri = 7;
-        let mut fi = 0;
-        for c in chars.by_ref() {
-            if c == ' ' {
-                break;
-            }
-            if c == '/' {
-                ri -= 1;
-                fi = 0;
-                continue;
-            }
+        let fields = fen::fields(&fen);
+        let err = |kind, field, offset, text: &str| FenError::new(kind, field, offset, text);
 
-            if ('1'..='8').contains(&c) {
-                fi += c as u8 - b'0';
-                continue;
-            }
-
-            if fi >= 8 || ri < 0 {
-                return Err(BoardCreationError::BoardOverflow);
-            }
-            let f: File = unsafe { transmute(fi) };
-            let r: Rank = unsafe { transmute(ri as u8) };
-
-            let s = Square::build(f, r);
+        if fields.is_empty() {
+            return Err(err(NoFenGiven, 0, 0, "").into());
+        }
+        if let Some(&(offset, text)) = fields.get(FenError::FIELDS.len()) {
+            return Err(err(TrailingGarbage, FenError::FIELDS.len(), offset, text).into());
+        }
+        let field = |i: usize| {
+            fields
+                .get(i)
+                .copied()
+                .ok_or_else(|| err(MissingField, i, fen.chars().count(), ""))
+        };
 
-            let color = match c {
-                'A'..='Z' => Color::White,
-                _ => Color::Black,
-            };
-            let pt = match c.to_ascii_lowercase() {
-                'p' => PieceType::Pawn,
-                'n' => PieceType::Knight,
-                'b' => PieceType::Bishop,
-                'r' => PieceType::Rook,
-                'q' => PieceType::Queen,
-                'k' => PieceType::King,
-                _ => return Err(BoardCreationError::InvalidPiece),
-            };
+        let (offset, placement) = field(0)?;
+        if placement.split('/').count() != 8 {
+            return Err(err(WrongRankCount, 0, offset, placement).into());
+        }
 
-            let p = pt + color;
+        let mut rank_offset = offset;
+        for (ri, rank) in placement.split('/').enumerate() {
+            let r = unsafe { transmute::<u8, Rank>(7 - ri as u8) };
+            let mut fi = 0;
 
-            b.add_piece(s, p);
+            for (ci, c) in rank.chars().enumerate() {
+                if let Some(n) = c.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                    fi += n as u8;
+                } else {
+                    let pt = match c.to_ascii_lowercase() {
+                        'p' => PieceType::Pawn,
+                        'n' => PieceType::Knight,
+                        'b' => PieceType::Bishop,
+                        'r' => PieceType::Rook,
+                        'q' => PieceType::Queen,
+                        'k' => PieceType::King,
+                        _ => {
+                            let at = rank_offset + ci;
+                            return Err(err(InvalidPiece, 0, at, &c.to_string()).into());
+                        }
+                    };
+                    let color = if c.is_ascii_uppercase() {
+                        Color::White
+                    } else {
+                        Color::Black
+                    };
 
-            fi += 1;
-        }
+                    if fi < 8 {
+                        let f = unsafe { transmute::<u8, File>(fi) };
+                        b.add_piece(Square::build(f, r), pt + color);
+                    }
+                    fi += 1;
+                }
 
-        if let Some(c) = chars.next() {
-            b.to_move = match c {
-                'w' => Color::White,
-                'b' => Color::Black,
-                _ => return Err(BoardCreationError::InvalidColor),
-            };
-        } else {
-            return Err(BoardCreationError::InvalidColor);
-        }
+                if fi > 8 {
+                    return Err(err(BoardOverflow, 0, rank_offset, rank).into());
+                }
+            }
 
-        if let Some(c) = chars.next() {
-            if c != ' ' {
-                return Err(BoardCreationError::InvalidCastleRights);
+            if fi < 8 {
+                return Err(err(BoardUnderflow, 0, rank_offset, rank).into());
             }
-        } else {
-            return Err(BoardCreationError::InvalidCastleRights);
+            rank_offset += rank.chars().count() + 1;
         }
 
-        let mut last: char = 0 as char;
-        for c in chars.by_ref() {
-            last = c;
-            if c == ' ' {
-                break;
-            }
-            if c == '-' {
-                if !state.castle_rights.all_none() {
-                    return Err(BoardCreationError::InvalidCastleRights);
-                }
-                break;
-            }
+        let (offset, color) = field(1)?;
+        b.to_move = match color {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(err(InvalidColor, 1, offset, color).into()),
+        };
 
-            match c {
-                'K' => {
-                    state.castle_rights.white_short = Some(CastleRight {
-                        king_from: Square::E1,
-                        king_to: Square::G1,
-                        rook_from: Square::H1,
-                        rook_to: Square::F1,
-                    })
-                }
-                'Q' => {
-                    state.castle_rights.white_long = Some(CastleRight {
-                        king_from: Square::E1,
-                        king_to: Square::C1,
-                        rook_from: Square::A1,
-                        rook_to: Square::D1,
-                    })
-                }
-                'k' => {
-                    state.castle_rights.black_short = Some(CastleRight {
-                        king_from: Square::E8,
-                        king_to: Square::G8,
-                        rook_from: Square::H8,
-                        rook_to: Square::F8,
-                    })
-                }
-                'q' => {
-                    state.castle_rights.black_long = Some(CastleRight {
-                        king_from: Square::E8,
-                        king_to: Square::C8,
-                        rook_from: Square::A8,
-                        rook_to: Square::D8,
-                    })
+        let (offset, castling) = field(2)?;
+        if castling != "-" {
+            for (ci, c) in castling.chars().enumerate() {
+                let invalid = || err(InvalidCastleRights, 2, offset + ci, &c.to_string());
+                let (color, short) = match c {
+                    'K' => (Color::White, true),
+                    'Q' => (Color::White, false),
+                    'k' => (Color::Black, true),
+                    'q' => (Color::Black, false),
+                    _ => return Err(invalid().into()),
+                };
+
+                let [short_right, long_right] = state.castle_rights.mut_rights_for(color);
+                let right = if short { short_right } else { long_right };
+                if right.is_some() {
+                    return Err(invalid().into());
                 }
-                _ => return Err(BoardCreationError::InvalidCastleRights),
+                *right = Some(CastleRight::standard(color, short));
             }
         }
 
-        if last == 0 as char {
-            return Err(BoardCreationError::InvalidCastleRights);
-        }
+        let (offset, ep) = field(3)?;
+        state.en_passant = match ep.as_bytes() {
+            b"-" => None,
+            &[f @ b'a'..=b'h', r @ b'1'..=b'8'] => unsafe {
+                Some(Square::build(
+                    transmute::<u8, File>(f - b'a'),
+                    transmute::<u8, Rank>(r - b'1'),
+                ))
+            },
+            _ => return Err(err(InvalidEnPassant, 3, offset, ep).into()),
+        };
 
-        if last != ' ' {
-            if let Some(c) = chars.next() {
-                // Consume the next char
-                if c != ' ' {
-                    return Err(BoardCreationError::InvalidEnPassant);
-                }
-            } else {
-                return Err(BoardCreationError::InvalidEnPassant);
-            }
+        if let Ok((offset, half_moves)) = field(4) {
+            state.half_moves = half_moves
+                .parse::<usize>()
+                .map_err(|_| err(InvalidNumber, 4, offset, half_moves))?;
         }
 
-        if let Some(c) = chars.next() {
-            if c == '-' {
-                state.en_passant = None;
-            } else if let Some(nc) = chars.next() {
-                unsafe {
-                    if !('a'..='h').contains(&c) || !('1'..='8').contains(&nc) {
-                        return Err(BoardCreationError::InvalidEnPassant);
-                    }
-                    let f: File = transmute(c as u8 - b'a');
-                    let r: Rank = transmute(nc as u8 - b'1');
-                    let eps = Square::build(f, r);
-                    state.en_passant = Some(eps);
-                }
-            } else {
-                return Err(BoardCreationError::InvalidEnPassant);
-            }
-        } else {
-            return Err(BoardCreationError::InvalidEnPassant);
+        if let Ok((offset, full_moves)) = field(5) {
+            b.ply = match full_moves.parse::<usize>() {
+                Ok(ply) if ply > 0 => ply,
+                _ => return Err(err(InvalidNumber, 5, offset, full_moves).into()),
+            };
         }
 
-        if let Some(c) = chars.next() {
-            if c != ' ' {
-                return Err(BoardCreationError::InvalidNumber);
-            }
-            let left_chars = chars.collect::<Vec<char>>();
-            let two_nums = left_chars.split(|&c| c == ' ').collect::<Vec<&[char]>>();
-            let fnum = two_nums[0];
+        b.set_up(state, strict)?;
+        Ok(b)
+    }
 
-            if let Ok(halfmoves) = fnum.iter().collect::<String>().parse::<usize>() {
-                state.half_moves = halfmoves;
-            } else {
-                return Err(BoardCreationError::InvalidNumber);
-            }
+    /// A [`Board`] with no pieces on it, white to move
+    fn empty() -> Self {
+        Self {
+            color_bb: [Bitboard::ZERO; Color::COUNT],
+            piece_bb: [Bitboard::ZERO; PieceType::COUNT],
+            pieces: [None; Square::COUNT],
+            piece_count: [0; PieceType::COUNT * Color::COUNT],
+            to_move: Color::White,
+            ply: 0,
 
-            if let Some(snum) = two_nums.get(1) {
-                if let Ok(ply) = snum.iter().collect::<String>().parse::<usize>() {
-                    if ply == 0 {
-                        return Err(BoardCreationError::InvalidNumber);
-                    }
-                    b.ply = ply;
-                } else {
-                    return Err(BoardCreationError::InvalidNumber);
-                }
-            }
+            history: Vec::with_capacity(2usize.pow(11)),
+            is960: false,
         }
+    }
 
-        b.compute_state(state);
-        Ok(b)
+    /// Validate a freshly placed position and fill in the derived parts of its [`State`]
+    fn set_up(&self, state: &mut State, strict: bool) -> Result<(), PositionError> {
+        self.validate_kings()?;
+        self.compute_state(state);
+        if strict {
+            self.validate(state)
+        } else {
+            self.validate_state(state)
+        }
     }
 
     pub fn clone(&self, state: &State) -> (Self, State) {
@@ -798,6 +898,8 @@ impl State {
             blockers: [Bitboard::ZERO; Color::COUNT],
             pinners: [Bitboard::ZERO; Color::COUNT],
             captured_piece: None,
+            dirty: [None; 3],
+            attacks: [None; Color::COUNT],
             prev: None,
         }
     }
@@ -812,6 +914,11 @@ impl State {
         self.en_passant
     }
 
+    /// Get the count of half moves since the last capture or pawn move
+    pub const fn half_moves(&self) -> usize {
+        self.half_moves
+    }
+
     /// Get the current count of plies from null (null being the start)
     pub const fn plies_from_null(&self) -> usize {
         self.plies_from_null
@@ -832,6 +939,16 @@ impl State {
         self.pinners[color.to_usize()]
     }
 
+    /// The pieces the last move changed
+    pub(crate) fn dirty_pieces(&self) -> impl Iterator<Item = DirtyPiece> {
+        self.dirty.into_iter().flatten()
+    }
+
+    fn push_dirty(&mut self, piece: Piece, from: Option<Square>, to: Option<Square>) {
+        let slot = self.dirty.iter_mut().find(|d| d.is_none());
+        *slot.expect("a move changes at most three pieces") = Some(DirtyPiece { piece, from, to });
+    }
+
     unsafe fn make_own_child(self) -> Self {
         let mut s = self; // Copy!
                           // Make a heap-allocated State variable
@@ -847,7 +964,7 @@ impl State {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// A wrapper that denotes certain castling privileges
 pub struct CastleRights {
     pub white_short: Option<CastleRight>,
@@ -856,7 +973,7 @@ pub struct CastleRights {
     pub black_long: Option<CastleRight>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CastleRight {
     pub king_from: Square,
     pub king_to: Square,
@@ -864,6 +981,25 @@ pub struct CastleRight {
     pub rook_to: Square,
 }
 
+impl CastleRight {
+    /// The right to castle `short` (king side) or long (queen side) for
+    /// `color` from the standard starting position
+    pub const fn standard(color: Color, short: bool) -> Self {
+        let (king_to, rook_from, rook_to) = if short {
+            (Square::G1, Square::H1, Square::F1)
+        } else {
+            (Square::C1, Square::A1, Square::D1)
+        };
+
+        Self {
+            king_from: Square::E1.relative_to(color),
+            king_to: king_to.relative_to(color),
+            rook_from: rook_from.relative_to(color),
+            rook_to: rook_to.relative_to(color),
+        }
+    }
+}
+
 impl CastleRights {
     pub const fn new() -> Self {
         Self {
@@ -917,15 +1053,43 @@ impl CastleRights {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The reasons [`Board::new`] may fail
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BoardCreationError {
-    NoFenGiven,
-    BoardOverflow,
-    InvalidPiece,
-    InvalidColor,
-    InvalidCastleRights,
-    InvalidEnPassant,
-    InvalidNumber,
+    /// The FEN could not be read
+    Fen(FenError),
+    /// The FEN was read, but describes an impossible position
+    InvalidPosition(PositionError),
+}
+
+impl From<FenError> for BoardCreationError {
+    fn from(e: FenError) -> Self {
+        Self::Fen(e)
+    }
+}
+
+impl From<PositionError> for BoardCreationError {
+    fn from(e: PositionError) -> Self {
+        Self::InvalidPosition(e)
+    }
+}
+
+impl fmt::Display for BoardCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Fen(e) => write!(f, "invalid FEN: {e}"),
+            Self::InvalidPosition(e) => write!(f, "invalid position: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BoardCreationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Fen(e) => Some(e),
+            Self::InvalidPosition(e) => Some(e),
+        }
+    }
 }
 
 impl fmt::Display for Board {
@@ -959,3 +1123,100 @@ impl fmt::Display for Board {
         write!(f, "{s}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::move_new;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    use Color::*;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn attack_maps() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let b = Board::new(Board::KIWIPETE, &mut s).unwrap();
+        for color in [White, Black] {
+            let map = b.attacks_by(&mut s, color);
+            for i in 0..Square::COUNT as u8 {
+                let sq = Square::new(i);
+                assert_eq!((map & sq).gtz(), b.is_square_attacked(sq, color), "{sq:?}");
+            }
+        }
+        assert_eq!(
+            b.attacks_by_piece(&mut s, White, PieceType::Knight),
+            Bitboard::from([
+                Square::A2,
+                Square::A4,
+                Square::B1,
+                Square::B5,
+                Square::D1,
+                Square::D5,
+                Square::E4,
+                Square::C4,
+                Square::C6,
+                Square::D3,
+                Square::D7,
+                Square::E2,
+                Square::F3,
+                Square::F7,
+                Square::G4,
+                Square::G6,
+            ])
+        );
+    }
+
+    #[test]
+    fn threats() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let b = Board::new("4k3/8/8/n2q4/8/8/3R4/3RK3 w - - 0 1", &mut s).unwrap();
+        assert_eq!(b.hanging_pieces(&mut s, Black), Bitboard::from(Square::D5));
+        assert_eq!(b.hanging_pieces(&mut s, White), Bitboard::ZERO);
+        assert_eq!(
+            b.x_ray_attacks(Square::D5, White),
+            Bitboard::from(Square::D1)
+        );
+        assert_eq!(b.x_ray_attacks(Square::D5, Black), Bitboard::ZERO);
+        assert!(b.is_square_attacked(Square::D4, White));
+        assert!(!b.is_square_attacked(Square::H8, White));
+    }
+
+    #[test]
+    fn null_move() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let mut b = Board::new("4k3/8/8/8/3pP3/8/8/4K2R b K e3 7 1", &mut s).unwrap();
+        let moves = crate::movegen::generate_legal(&b, &s).len();
+        let plies_from_null = s.plies_from_null();
+
+        b.do_null_move(&mut s).unwrap();
+        assert_eq!(b.to_move(), White);
+        assert_eq!(s.en_passant(), None);
+        assert_eq!(s.half_moves(), 8);
+        assert_eq!(s.plies_from_null(), 0);
+        assert!(s.castle_rights().white_short.is_some());
+
+        // Rh8 is check, so black can't pass back
+        let check = move_new!(Square::H1, Square::H8);
+        b.do_move(&mut s, check).unwrap();
+        assert!(s.checkers().gtz());
+        assert_eq!(b.do_null_move(&mut s), Err(Move::NULL));
+        assert_eq!(b.to_move(), Black);
+        b.undo_move(&mut s, check);
+
+        b.undo_null_move(&mut s);
+        assert_eq!(b.to_move(), Black);
+        assert_eq!(s.en_passant(), Some(Square::E3));
+        assert_eq!(s.half_moves(), 7);
+        assert_eq!(s.plies_from_null(), plies_from_null);
+        assert_eq!(crate::movegen::generate_legal(&b, &s).len(), moves);
+    }
+}
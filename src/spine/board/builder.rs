@@ -0,0 +1,236 @@
+use super::{Board, State};
+use crate::prelude::*;
+
+/// A way to set up a position piece by piece instead of through a FEN.
+///
+/// Nothing is checked until [`BoardBuilder::build`], which validates the
+/// position and computes its [`State`].
+#[derive(Debug, Clone)]
+pub struct BoardBuilder {
+    pieces: [Option<Piece>; Square::COUNT],
+    to_move: Color,
+    castle_rights: CastleRights,
+    en_passant: Option<Square>,
+    half_moves: usize,
+    full_moves: usize,
+}
+
+impl BoardBuilder {
+    /// Start from an empty board, white to move, with no castling rights
+    pub const fn new() -> Self {
+        Self {
+            pieces: [None; Square::COUNT],
+            to_move: Color::White,
+            castle_rights: CastleRights::new(),
+            en_passant: None,
+            half_moves: 0,
+            full_moves: 1,
+        }
+    }
+
+    /// Start from an existing position, to edit it
+    pub fn from_board(board: &Board, state: &State) -> Self {
+        Self {
+            pieces: board.pieces,
+            to_move: board.to_move(),
+            castle_rights: state.castle_rights(),
+            en_passant: state.en_passant(),
+            half_moves: state.half_moves,
+            full_moves: board.ply(),
+        }
+    }
+
+    /// The [`Piece`] currently on `square`, if any
+    pub const fn get(&self, square: Square) -> Option<Piece> {
+        self.pieces[square.to_usize()]
+    }
+
+    /// Put `piece` on `square`, replacing whatever was there
+    pub fn put(&mut self, square: Square, piece: Piece) -> &mut Self {
+        self.pieces[square.to_usize()] = Some(piece);
+        self
+    }
+
+    /// Remove whatever is on `square`
+    pub fn clear(&mut self, square: Square) -> &mut Self {
+        self.pieces[square.to_usize()] = None;
+        self
+    }
+
+    /// Set the [`Color`] to move
+    pub fn side_to_move(&mut self, color: Color) -> &mut Self {
+        self.to_move = color;
+        self
+    }
+
+    /// Give `color` the standard right to castle `short` (king side) or long
+    pub fn castling(&mut self, color: Color, short: bool) -> &mut Self {
+        let [short_right, long_right] = self.castle_rights.mut_rights_for(color);
+        let right = if short { short_right } else { long_right };
+        *right = Some(CastleRight::standard(color, short));
+        self
+    }
+
+    /// Replace all of the castling rights at once
+    pub fn castle_rights(&mut self, rights: CastleRights) -> &mut Self {
+        self.castle_rights = rights;
+        self
+    }
+
+    /// Set the en passant square (the one that the capture takes place on)
+    pub fn en_passant(&mut self, square: Option<Square>) -> &mut Self {
+        self.en_passant = square;
+        self
+    }
+
+    /// Set the count of half moves since the last capture or pawn move
+    pub fn half_moves(&mut self, half_moves: usize) -> &mut Self {
+        self.half_moves = half_moves;
+        self
+    }
+
+    /// Set the move number, as in the last field of a FEN
+    pub fn full_moves(&mut self, full_moves: usize) -> &mut Self {
+        self.full_moves = full_moves;
+        self
+    }
+
+    /// Create the [`Board`] and its [`State`], as long as the position
+    /// passes [`Board::validate`]
+    pub fn build(&self) -> Result<(Board, State), PositionError> {
+        self.build_inner(true)
+    }
+
+    /// Like [`BoardBuilder::build`], with the checks of [`Board::new_lenient`]
+    pub fn build_lenient(&self) -> Result<(Board, State), PositionError> {
+        self.build_inner(false)
+    }
+
+    fn build_inner(&self, strict: bool) -> Result<(Board, State), PositionError> {
+        let mut board = Board::empty();
+        let mut state = State::new();
+
+        for (i, p) in self.pieces.iter().enumerate() {
+            if let Some(p) = *p {
+                board.add_piece(Square::new(i as u8), p);
+            }
+        }
+
+        board.to_move = self.to_move;
+        board.ply = self.full_moves;
+        state.castle_rights = self.castle_rights;
+        state.en_passant = self.en_passant;
+        state.half_moves = self.half_moves;
+
+        board.set_up(&mut state, strict)?;
+        Ok((board, state))
+    }
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen;
+    use crate::perft;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    use Color::*;
+    use PieceType::*;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn startpos() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut builder = BoardBuilder::new();
+        let back_rank = [Rook, Knight, Bishop, Queen, King, Bishop, Knight, Rook];
+        for (i, pt) in back_rank.into_iter().enumerate() {
+            let sq = Square::new(i as u8);
+            builder
+                .put(sq, pt + White)
+                .put(sq + ShiftDir::Forward(White), Pawn + White)
+                .put(sq.relative_to(Black), pt + Black)
+                .put(
+                    sq.relative_to(Black) + ShiftDir::Forward(Black),
+                    Pawn + Black,
+                );
+        }
+        for color in [White, Black] {
+            builder.castling(color, true).castling(color, false);
+        }
+
+        let (mut board, mut state) = builder.build().unwrap();
+        let mut fen_state = State::new();
+        let fen_board = Board::new(Board::STARTPOS, &mut fen_state).unwrap();
+
+        for i in 0..Square::COUNT as u8 {
+            let sq = Square::new(i);
+            assert_eq!(board.get_piece(sq), fen_board.get_piece(sq));
+        }
+        assert_eq!(state.castle_rights(), fen_state.castle_rights());
+        assert_eq!(perft::perft_on(&mut board, &mut state, 3), 8902);
+    }
+
+    #[test]
+    fn edit_existing() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let b = Board::new(Board::KIWIPETE, &mut s).unwrap();
+
+        // Taking away the h1 rook must take the castling right with it
+        let mut builder = BoardBuilder::from_board(&b, &s);
+        assert_eq!(builder.get(Square::H1), Some(Rook + White));
+        assert!(matches!(
+            builder.clear(Square::H1).build(),
+            Err(PositionError::InvalidCastleRight(_))
+        ));
+
+        let mut rights = s.castle_rights();
+        rights.white_short = None;
+        let (b, s) = builder.castle_rights(rights).build().unwrap();
+        assert!(b.get_piece(Square::H1).is_none());
+        assert!(s.castle_rights().white_short.is_none());
+    }
+
+    #[test]
+    fn validates() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut builder = BoardBuilder::new();
+        builder.put(Square::E1, King + White);
+        assert_eq!(
+            builder.build().unwrap_err(),
+            PositionError::MissingKing(Black)
+        );
+
+        builder
+            .put(Square::E8, King + Black)
+            .put(Square::E7, Pawn + Black)
+            .put(Square::D5, Pawn + White)
+            .side_to_move(Black);
+        assert!(builder.build().is_ok());
+
+        // Move the pawn as if it had just been pushed twice
+        builder
+            .clear(Square::E7)
+            .put(Square::E5, Pawn + Black)
+            .side_to_move(White)
+            .en_passant(Some(Square::E6));
+        let (b, s) = builder.build().unwrap();
+        assert_eq!(s.en_passant(), Some(Square::E6));
+        assert_eq!(movegen::generate_legal(&b, &s).len(), 7);
+
+        builder.put(Square::A1, Pawn + White);
+        assert!(builder.build().is_err());
+        assert!(builder.build_lenient().is_ok());
+    }
+}
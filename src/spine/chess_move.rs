@@ -79,20 +79,14 @@ impl fmt::Debug for Move {
 
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let map_promo = |t: PieceType| match t {
-            PieceType::Pawn | PieceType::King => '\x00',
-            PieceType::Knight => 'n',
-            PieceType::Bishop => 'b',
-            PieceType::Rook => 'r',
-            PieceType::Queen => 'q',
+        let promo = match self.promotion_type() {
+            PieceType::Pawn | PieceType::King => "",
+            PieceType::Knight => "n",
+            PieceType::Bishop => "b",
+            PieceType::Rook => "r",
+            PieceType::Queen => "q",
         };
 
-        write!(
-            f,
-            "{}{}{}",
-            self.from_square(),
-            self.to_square(),
-            map_promo(self.promotion_type())
-        )
+        write!(f, "{}{}{promo}", self.from_square(), self.to_square())
     }
 }
@@ -0,0 +1,365 @@
+//! Knowledge of endgames that doesn't need tablebases on disk.
+//!
+//! King and pawn against king is decided exactly by a bitbase, built by
+//! retrograde analysis the first time it is needed. A handful of other
+//! material balances are recognised and given a score that leads the side
+//! with the extra material towards mate, or are called drawn.
+
+use crate::piece_attacks::{king_attacks, pawn_attacks};
+use crate::prelude::*;
+
+use std::sync::OnceLock;
+
+use PieceType::*;
+
+const QUEEN_VALUE: i32 = 900;
+const ROOK_VALUE: i32 = 500;
+const PAWN_VALUE: i32 = 100;
+
+/// Score the position if its material is one the module knows about,
+/// from the point of view of the side to move.
+///
+/// The position must not be checkmate or stalemate; finding those is up to
+/// the caller. For KQK, KRK and KBNK, a position where the lone king can
+/// take an undefended piece is not scored.
+pub fn probe(board: &Board, _state: &State) -> Option<Score> {
+    for strong in [Color::White, Color::Black] {
+        let weak = !strong;
+        if board.color(weak).more_than_one() {
+            continue;
+        }
+
+        let score = match strong_material(board, strong) {
+            [0, 0, 0, 0, 0] => Some(Score::DRAW),
+            [1, 0, 0, 0, 0] => Some(kpk(board, strong)),
+            [0, 1, 0, 0, 0] | [0, 0, 1, 0, 0] | [0, 2, 0, 0, 0] => Some(Score::DRAW),
+            [0, 1, 1, 0, 0] => kbnk(board, strong),
+            [0, 0, 0, 1, 0] => kxk(board, strong, Rook, ROOK_VALUE),
+            [0, 0, 0, 0, 1] => kxk(board, strong, Queen, QUEEN_VALUE),
+            _ => None,
+        };
+
+        return score.map(|s| if board.to_move() == strong { s } else { -s });
+    }
+
+    // A minor piece each, and nothing else
+    let only_minor = |c| {
+        strong_material(board, c) == [0, 1, 0, 0, 0] || strong_material(board, c) == [0, 0, 1, 0, 0]
+    };
+    if only_minor(Color::White) && only_minor(Color::Black) {
+        return Some(Score::DRAW);
+    }
+
+    None
+}
+
+/// The number of pawns, knights, bishops, rooks and queens `color` has
+fn strong_material(board: &Board, color: Color) -> [i8; 5] {
+    [Pawn, Knight, Bishop, Rook, Queen].map(|pt| board.piece_count(color, pt))
+}
+
+/// Larger the closer `s` is to the edge of the board
+fn edge_bonus(s: Square) -> i32 {
+    let centre = |i: usize| (7 - 2 * i as i32).abs() / 2;
+    20 * (centre(s.file().to_usize()) + centre(s.rank().to_usize()))
+}
+
+/// Larger the closer the two squares are
+fn closeness_bonus(a: Square, b: Square) -> i32 {
+    10 * (7 - a.distance(b))
+}
+
+/// A lone king against a king and a queen or rook: drive the king to the edge
+fn kxk(board: &Board, strong: Color, pt: PieceType, value: i32) -> Option<Score> {
+    let (sk, wk) = (board.king(strong), board.king(!strong));
+    let piece = board.spec(strong, pt).lsb();
+
+    let hanging = (king_attacks(wk) & piece).gtz() && !(king_attacks(sk) & piece).gtz();
+    if board.to_move() != strong && hanging {
+        return None;
+    }
+
+    Some(Score::KNOWN_WIN + value + edge_bonus(wk) + closeness_bonus(sk, wk))
+}
+
+/// Bishop and knight: mate only happens in a corner of the bishop's colour
+fn kbnk(board: &Board, strong: Color) -> Option<Score> {
+    let (sk, wk) = (board.king(strong), board.king(!strong));
+    let bishop = board.spec(strong, Bishop).lsb();
+
+    // The other piece may guard it, even through the lone king
+    let occupied = board.all() ^ wk;
+    let hanging = (king_attacks(wk) & board.color(strong))
+        .any(|s| !(board.attacks_to_bits(s, occupied) & board.color(strong)).gtz());
+    if board.to_move() != strong && hanging {
+        return None;
+    }
+
+    let is_dark = |s: Square| (s.file().to_usize() + s.rank().to_usize()).is_multiple_of(2);
+    let corners = if is_dark(bishop) {
+        [Square::A1, Square::H8]
+    } else {
+        [Square::A8, Square::H1]
+    };
+    let corner_dist = corners.map(|c| wk.distance(c)).into_iter().min().unwrap();
+
+    Some(Score::KNOWN_WIN + 50 * (7 - corner_dist) + closeness_bonus(sk, wk))
+}
+
+/// King and pawn against king, from the bitbase
+fn kpk(board: &Board, strong: Color) -> Score {
+    // Look at it as if white has the pawn, on the queen side
+    let pawn = board.spec(strong, Pawn).lsb();
+    let flip = |s: Square| {
+        let s = s.relative_to(strong);
+        if pawn.file().to_usize() >= 4 {
+            Square::new(s.to_u8() ^ 7)
+        } else {
+            s
+        }
+    };
+
+    let white_to_move = board.to_move() == strong;
+    let (sk, wk, p) = (
+        flip(board.king(strong)),
+        flip(board.king(!strong)),
+        flip(pawn),
+    );
+
+    if kpk_wins(white_to_move, sk, wk, p) {
+        Score::KNOWN_WIN + PAWN_VALUE + 20 * p.rank().to_usize() as i32
+    } else {
+        Score::DRAW
+    }
+}
+
+/// Positions of the KPK bitbase: the side to move, both kings, and the pawn
+/// on files a to d and ranks 2 to 7.
+const KPK_SIZE: usize = 2 * 64 * 64 * 24;
+
+static KPK: OnceLock<Box<[u32]>> = OnceLock::new();
+
+/// Whether white, with a pawn on `pawn` (on files a to d), wins
+fn kpk_wins(white_to_move: bool, white_king: Square, black_king: Square, pawn: Square) -> bool {
+    let bits = KPK.get_or_init(build_kpk);
+    let idx = kpk_index(white_to_move, white_king, black_king, pawn);
+    bits[idx / 32] & (1 << (idx % 32)) != 0
+}
+
+fn kpk_index(white_to_move: bool, white_king: Square, black_king: Square, pawn: Square) -> usize {
+    debug_assert!(pawn.file().to_usize() < 4);
+    let rank = 6 - pawn.rank().to_usize();
+    white_king.to_usize()
+        | (black_king.to_usize() << 6)
+        | ((!white_to_move as usize) << 12)
+        | (pawn.file().to_usize() << 13)
+        | (rank << 15)
+}
+
+// The results of the retrograde analysis, as bit flags so that the results of
+// all the moves from a position can be or-ed together
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+struct KpkPosition {
+    white_to_move: bool,
+    kings: [Square; 2],
+    pawn: Square,
+}
+
+impl KpkPosition {
+    fn from_index(idx: usize) -> Self {
+        let file = (idx >> 13) & 3;
+        let rank = 6 - ((idx >> 15) & 7);
+        Self {
+            white_to_move: (idx >> 12) & 1 == 0,
+            kings: [
+                Square::new((idx & 63) as u8),
+                Square::new(((idx >> 6) & 63) as u8),
+            ],
+            pawn: Square::new((file + 8 * rank) as u8),
+        }
+    }
+
+    /// What can be said about the position without looking at any others
+    fn initial(&self) -> u8 {
+        let [wk, bk] = self.kings;
+        let push = self.pawn + ShiftDir::Forward(Color::White);
+
+        if wk.distance(bk) <= 1
+            || wk == self.pawn
+            || bk == self.pawn
+            || (self.white_to_move && (pawn_attacks(self.pawn, Color::White) & bk).gtz())
+        {
+            INVALID
+        } else if self.white_to_move
+            && self.pawn.rank() == Rank::Seven
+            && wk != push
+            && bk != push
+            && (bk.distance(push) > 1 || wk.distance(push) == 1)
+        {
+            // Promotes, and the queen can't be taken
+            WIN
+        } else if !self.white_to_move
+            && (!(king_attacks(bk) & !(king_attacks(wk) | pawn_attacks(self.pawn, Color::White)))
+                .gtz()
+                || (king_attacks(bk) & !king_attacks(wk) & self.pawn).gtz())
+        {
+            // Stalemate, or the pawn is lost
+            DRAW
+        } else {
+            UNKNOWN
+        }
+    }
+
+    /// Decide the position from the positions its moves lead to
+    fn classify(&self, db: &[u8]) -> u8 {
+        let [wk, bk] = self.kings;
+        let (good, bad) = if self.white_to_move {
+            (WIN, DRAW)
+        } else {
+            (DRAW, WIN)
+        };
+
+        let mut r = INVALID;
+        if self.white_to_move {
+            for s in king_attacks(wk) {
+                r |= db[kpk_index(false, s, bk, self.pawn)];
+            }
+
+            let push = self.pawn + ShiftDir::Forward(Color::White);
+            if self.pawn.rank() < Rank::Seven {
+                r |= db[kpk_index(false, wk, bk, push)];
+            }
+            if self.pawn.rank() == Rank::Two && push != wk && push != bk {
+                r |= db[kpk_index(false, wk, bk, push + ShiftDir::Forward(Color::White))];
+            }
+        } else {
+            for s in king_attacks(bk) {
+                r |= db[kpk_index(true, wk, s, self.pawn)];
+            }
+        }
+
+        if r & good != 0 {
+            good
+        } else if r & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            bad
+        }
+    }
+}
+
+fn build_kpk() -> Box<[u32]> {
+    let positions: Vec<_> = (0..KPK_SIZE).map(KpkPosition::from_index).collect();
+    let mut db: Vec<u8> = positions.iter().map(KpkPosition::initial).collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (i, pos) in positions.iter().enumerate() {
+            if db[i] == UNKNOWN {
+                db[i] = pos.classify(&db);
+                changed |= db[i] != UNKNOWN;
+            }
+        }
+    }
+
+    let mut bits = vec![0u32; KPK_SIZE / 32].into_boxed_slice();
+    for (i, &r) in db.iter().enumerate() {
+        if r == WIN {
+            bits[i / 32] |= 1 << (i % 32);
+        }
+    }
+
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    fn probe_fen(fen: &str) -> Option<Score> {
+        INIT.call_once(initialize_bitboards);
+        let mut s = State::new();
+        let b = Board::new(fen, &mut s).unwrap();
+        probe(&b, &s)
+    }
+
+    fn is_win(score: Option<Score>) -> bool {
+        score.is_some_and(|s| s >= Score::KNOWN_WIN)
+    }
+
+    #[test]
+    fn kpk() {
+        // King on the sixth in front of the pawn wins whoever moves
+        assert!(is_win(probe_fen("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")));
+        assert!(probe_fen("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").unwrap() <= -Score::KNOWN_WIN);
+
+        // The opposition
+        assert_eq!(
+            probe_fen("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1"),
+            Some(Score::DRAW)
+        );
+
+        // Rook pawns and lost pawns
+        assert_eq!(probe_fen("k7/8/K7/P7/8/8/8/8 w - - 0 1"), Some(Score::DRAW));
+        assert_eq!(
+            probe_fen("8/8/8/8/8/8/3kP3/7K b - - 0 1"),
+            Some(Score::DRAW)
+        );
+
+        // The same, from black's side and on the other wing
+        assert!(is_win(probe_fen("8/8/8/8/3p4/3k4/8/3K4 b - - 0 1")));
+        assert_eq!(probe_fen("8/8/8/8/8/k7/p7/K7 b - - 0 1"), Some(Score::DRAW));
+    }
+
+    #[test]
+    fn mating_material() {
+        let krk = probe_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let krk_edge = probe_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let kqk = probe_fen("8/8/8/4k3/8/8/8/1Q2K3 w - - 0 1").unwrap();
+        assert!(krk >= Score::KNOWN_WIN);
+        assert!(krk_edge > krk);
+        assert!(kqk > krk);
+
+        // The side to move is the one losing
+        assert!(probe_fen("8/8/8/4k3/8/8/8/R3K3 b - - 0 1").unwrap() <= -Score::KNOWN_WIN);
+        // ...and can take the rook
+        assert_eq!(probe_fen("8/8/8/8/8/8/3kR3/7K b - - 0 1"), None);
+
+        // Dark squared bishop, so a1 and h8
+        let right = probe_fen("7k/8/5K2/8/8/8/8/2B1N3 w - - 0 1").unwrap();
+        let wrong = probe_fen("k7/8/2K5/8/8/8/8/2B1N3 w - - 0 1").unwrap();
+        assert!(right > wrong);
+        assert!(wrong >= Score::KNOWN_WIN);
+
+        // The lone king can take the knight, unless the bishop guards it
+        assert_eq!(probe_fen("B7/8/8/8/8/8/3kN3/7K b - - 0 1"), None);
+        assert!(probe_fen("8/8/8/8/8/8/3kN3/5B1K b - - 0 1").unwrap() <= -Score::KNOWN_WIN);
+        assert!(probe_fen("8/8/8/B7/8/2k5/3N4/7K b - - 0 1").unwrap() <= -Score::KNOWN_WIN);
+        assert!(probe_fen("B7/8/8/8/8/8/3kN3/7K w - - 0 1").unwrap() >= Score::KNOWN_WIN);
+    }
+
+    #[test]
+    fn drawn_material() {
+        for fen in [
+            "8/8/4k3/8/8/8/8/4K3 w - - 0 1",
+            "8/8/4k3/8/8/8/8/4KN2 w - - 0 1",
+            "8/8/4k3/8/8/8/8/4KB2 b - - 0 1",
+            "8/8/4k3/8/8/8/8/3NKN2 w - - 0 1",
+            "8/8/4kb2/8/8/8/8/4KN2 w - - 0 1",
+        ] {
+            assert_eq!(probe_fen(fen), Some(Score::DRAW), "{fen}");
+        }
+
+        assert_eq!(probe_fen(Board::STARTPOS), None);
+        assert_eq!(probe_fen("8/8/4k3/8/8/8/4P3/4KN2 w - - 0 1"), None);
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt;
+
+/// What went wrong while reading a FEN, see [`FenError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenErrorKind {
+    /// The FEN was empty
+    NoFenGiven,
+    /// One of the required fields is missing
+    MissingField,
+    /// There is more text after the last field
+    TrailingGarbage,
+    /// The piece placement does not have exactly eight ranks
+    WrongRankCount,
+    /// A rank describes more than eight squares
+    BoardOverflow,
+    /// A rank describes fewer than eight squares
+    BoardUnderflow,
+    /// A character in the piece placement is neither a piece nor a digit from 1 to 8
+    InvalidPiece,
+    /// The side to move is not `w` or `b`
+    InvalidColor,
+    /// The castling rights are not `-` or some of `KQkq`
+    InvalidCastleRights,
+    /// The en passant square is not `-` or a square
+    InvalidEnPassant,
+    /// One of the move counters is not a valid number
+    InvalidNumber,
+}
+
+/// An error in the text of a FEN, pointing at where it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FenError {
+    kind: FenErrorKind,
+    field: usize,
+    offset: usize,
+    text: String,
+}
+
+impl FenError {
+    /// The names of the six FEN fields, in order
+    pub const FIELDS: [&'static str; 6] = [
+        "piece placement",
+        "side to move",
+        "castling rights",
+        "en passant square",
+        "halfmove clock",
+        "fullmove number",
+    ];
+
+    pub(crate) fn new(kind: FenErrorKind, field: usize, offset: usize, text: &str) -> Self {
+        Self {
+            kind,
+            field,
+            offset,
+            text: text.to_string(),
+        }
+    }
+
+    /// What kind of error this is
+    pub const fn kind(&self) -> FenErrorKind {
+        self.kind
+    }
+
+    /// The index of the (whitespace-separated) field the error is in
+    pub const fn field(&self) -> usize {
+        self.field
+    }
+
+    /// The character offset into the FEN where the offending text starts
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The offending text itself
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Split a FEN into its whitespace-separated fields, along with the character
+/// offset each one starts at.
+pub(crate) fn fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::with_capacity(FenError::FIELDS.len());
+    let mut start = None;
+
+    for (i, (byte, c)) in fen.char_indices().enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((i, byte)),
+            (Some((ci, b)), true) => {
+                fields.push((ci, &fen[b..byte]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some((ci, b)) = start {
+        fields.push((ci, &fen[b..]));
+    }
+
+    fields
+}
+
+impl fmt::Display for FenErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::NoFenGiven => "no FEN given",
+            Self::MissingField => "missing field",
+            Self::TrailingGarbage => "unexpected text after the last field",
+            Self::WrongRankCount => "expected exactly eight ranks",
+            Self::BoardOverflow => "rank has more than eight squares",
+            Self::BoardUnderflow => "rank has fewer than eight squares",
+            Self::InvalidPiece => "invalid piece",
+            Self::InvalidColor => "side to move must be `w` or `b`",
+            Self::InvalidCastleRights => "castling rights must be `-` or some of `KQkq`",
+            Self::InvalidEnPassant => "en passant square must be `-` or a square",
+            Self::InvalidNumber => "invalid number",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let field = FenError::FIELDS
+            .get(self.field)
+            .copied()
+            .unwrap_or("end of FEN");
+
+        write!(f, "{} in {field} at offset {}", self.kind, self.offset)?;
+        if !self.text.is_empty() {
+            write!(f, ": `{}`", self.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for FenError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    use FenErrorKind::*;
+
+    static INIT: Once = Once::new();
+
+    fn parse(fen: &str) -> Result<Board, FenError> {
+        INIT.call_once(initialize_bitboards);
+        match Board::new(fen, &mut State::new()) {
+            Ok(b) => Ok(b),
+            Err(BoardCreationError::Fen(e)) => Err(e),
+            Err(e) => panic!("{fen} is not a valid position: {e}"),
+        }
+    }
+
+    fn error(fen: &str) -> (FenErrorKind, usize, usize, String) {
+        let e = parse(fen).unwrap_err();
+        (e.kind(), e.field(), e.offset(), e.text().to_string())
+    }
+
+    #[test]
+    fn split_fields() {
+        assert_eq!(
+            fields("  8/8  w -\t- "),
+            vec![(2, "8/8"), (7, "w"), (9, "-"), (11, "-")]
+        );
+        assert!(fields(" ").is_empty());
+    }
+
+    #[test]
+    fn accepts_good_fens() {
+        assert!(parse(Board::STARTPOS).is_ok());
+        assert!(parse(Board::KIWIPETE).is_ok());
+        assert!(parse("4k3/8/8/8/8/8/8/4K3 b - - 12").is_ok());
+    }
+
+    #[test]
+    fn placement_errors() {
+        assert_eq!(error(""), (NoFenGiven, 0, 0, "".into()));
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+            (WrongRankCount, 0, 0, "4k3/8/8/8/8/8/4K3".into())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3p w - - 0 1"),
+            (BoardOverflow, 0, 16, "4K3p".into())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/7/8/8/4K3 w - - 0 1"),
+            (BoardUnderflow, 0, 10, "7".into())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/3x4/8/8/4K3 w - - 0 1"),
+            (InvalidPiece, 0, 11, "x".into())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/3049/8/8/4K3 w - - 0 1"),
+            (InvalidPiece, 0, 11, "0".into())
+        );
+    }
+
+    #[test]
+    fn field_errors() {
+        let board = "4k3/8/8/8/8/8/8/4K3";
+        assert_eq!(error(board), (MissingField, 1, 19, "".into()));
+        assert_eq!(
+            error(&format!("{board} white - - 0 1")),
+            (InvalidColor, 1, 20, "white".into())
+        );
+        assert_eq!(
+            error(&format!("{board} w KX - 0 1")),
+            (InvalidCastleRights, 2, 23, "X".into())
+        );
+        assert_eq!(
+            error(&format!("{board} w -- - 0 1")),
+            (InvalidCastleRights, 2, 22, "-".into())
+        );
+        assert_eq!(
+            error(&format!("{board} w - e9 0 1")),
+            (InvalidEnPassant, 3, 24, "e9".into())
+        );
+        assert_eq!(
+            error(&format!("{board} w - - x 1")),
+            (InvalidNumber, 4, 26, "x".into())
+        );
+        assert_eq!(
+            error(&format!("{board} w - - 0 0")),
+            (InvalidNumber, 5, 28, "0".into())
+        );
+        assert_eq!(
+            error(&format!("{board} w - - 0 1 extra")),
+            (TrailingGarbage, 6, 30, "extra".into())
+        );
+    }
+
+    #[test]
+    fn display() {
+        let e = parse("4k3/8/8/8/3x4/8/8/4K3 w - - 0 1").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "invalid piece in piece placement at offset 11: `x`"
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! Solving and checking composed "mate in N" problems.
+//!
+//! [`solve`] finds every key: every first move for the side to move that
+//! mates in at most N moves against any defence. A problem is sound when
+//! there is exactly one; any other key is a cook, a quicker mate included.
+//! Beneath each key, [`Solution`] keeps every defence together with every
+//! attacking move that mates soonest after it. A defence with more than
+//! one such move has a dual; slower moves that would still mate in time
+//! are left out, as they are not duals.
+//!
+//! Draws by repetition or the fifty-move rule are not considered, as is
+//! usual for problems.
+
+use crate::movegen;
+use crate::prelude::*;
+
+/// An attacking move that forces mate, and every defence to it. There are
+/// no defences when the move mates at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub mv: Move,
+    pub defences: Vec<Defence>,
+}
+
+/// A defending move, and every attacking move that mates soonest after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Defence {
+    pub mv: Move,
+    pub continuations: Vec<Line>,
+}
+
+/// Everything [`solve`] found out about a problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// The number of attacking moves the problem allows
+    pub moves: usize,
+    /// Every key, with its full solution tree
+    pub keys: Vec<Line>,
+}
+
+impl Solution {
+    /// Whether mate in the given number of moves was proven at all
+    pub fn is_proven(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Whether there is exactly one key
+    pub fn is_sound(&self) -> bool {
+        self.keys.len() == 1
+    }
+
+    /// The keys after the first, if the problem is cooked
+    pub fn cooks(&self) -> &[Line] {
+        self.keys.get(1..).unwrap_or_default()
+    }
+
+    /// Every defence that can be answered by more than one mating
+    /// continuation, as the moves leading up to and including it
+    pub fn duals(&self) -> Vec<Vec<Move>> {
+        let mut found = Vec::new();
+        let mut path = Vec::new();
+        for key in &self.keys {
+            find_duals(key, &mut path, &mut found);
+        }
+        found
+    }
+
+    /// The solution tree in SAN, one line per move, from the position it
+    /// was solved in
+    pub fn to_text(&self, board: &Board, state: &State) -> String {
+        let (mut board, mut state) = board.clone(state);
+        let mut text = String::new();
+        for (i, key) in self.keys.iter().enumerate() {
+            let mark = if i == 0 { "!" } else { "! (cook)" };
+            write_line(&mut board, &mut state, key, 1, 0, mark, &mut text);
+        }
+        text
+    }
+}
+
+fn find_duals(line: &Line, path: &mut Vec<Move>, found: &mut Vec<Vec<Move>>) {
+    path.push(line.mv);
+    for defence in &line.defences {
+        path.push(defence.mv);
+        if defence.continuations.len() > 1 {
+            found.push(path.clone());
+        }
+        for next in &defence.continuations {
+            find_duals(next, path, found);
+        }
+        let _ = path.pop();
+    }
+    let _ = path.pop();
+}
+
+fn write_line(
+    board: &mut Board,
+    state: &mut State,
+    line: &Line,
+    number: usize,
+    indent: usize,
+    mark: &str,
+    text: &mut String,
+) {
+    let san = board.to_san(state, line.mv);
+    text.push_str(&format!("{:indent$}{number}. {san}{mark}\n", ""));
+
+    board.do_move(state, line.mv).unwrap();
+    for defence in &line.defences {
+        let san = board.to_san(state, defence.mv);
+        text.push_str(&format!("{:w$}{number}... {san}\n", "", w = indent + 2));
+
+        board.do_move(state, defence.mv).unwrap();
+        for next in &defence.continuations {
+            write_line(board, state, next, number + 1, indent + 4, "", text);
+        }
+        board.undo_move(state, defence.mv);
+    }
+    board.undo_move(state, line.mv);
+}
+
+/// Find every way for the side to move to mate in at most `moves` moves.
+/// With `checks_only`, every attacking move has to give check.
+///
+/// # Panics
+///
+/// If `moves` is zero.
+pub fn solve(board: &Board, state: &State, moves: usize, checks_only: bool) -> Solution {
+    assert!(moves > 0, "a problem needs at least one move");
+
+    let (mut board, mut state) = board.clone(state);
+    let keys = mating_lines(&mut board, &mut state, moves, checks_only);
+    Solution { moves, keys }
+}
+
+/// The attacking moves that mate within `moves` moves
+fn mating_lines(
+    board: &mut Board,
+    state: &mut State,
+    moves: usize,
+    checks_only: bool,
+) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for &mv in movegen::generate_legal(board, state).iter() {
+        board.do_move(state, mv).unwrap();
+        if is_allowed(state, moves, checks_only) && is_lost(board, state, moves - 1, checks_only) {
+            let defences = movegen::generate_legal(board, state)
+                .iter()
+                .map(|&reply| {
+                    board.do_move(state, reply).unwrap();
+                    let continuations = quickest_lines(board, state, moves - 1, checks_only);
+                    board.undo_move(state, reply);
+                    Defence {
+                        mv: reply,
+                        continuations,
+                    }
+                })
+                .collect();
+            lines.push(Line { mv, defences });
+        }
+        board.undo_move(state, mv);
+    }
+    lines
+}
+
+/// The attacking moves that mate soonest, if that is within `moves` moves
+fn quickest_lines(
+    board: &mut Board,
+    state: &mut State,
+    moves: usize,
+    checks_only: bool,
+) -> Vec<Line> {
+    (1..=moves)
+        .map(|n| mating_lines(board, state, n, checks_only))
+        .find(|lines| !lines.is_empty())
+        .unwrap_or_default()
+}
+
+/// Whether the attacking move just played can be part of a solution with
+/// `moves` moves to go, counting itself. The last one has to give check
+/// to mate.
+fn is_allowed(state: &State, moves: usize, checks_only: bool) -> bool {
+    state.checkers().gtz() || (!checks_only && moves > 1)
+}
+
+/// Whether the side to move is mated, or will be within `moves` more
+/// attacking moves whatever it does
+fn is_lost(board: &mut Board, state: &mut State, moves: usize, checks_only: bool) -> bool {
+    let replies = movegen::generate_legal(board, state);
+    if replies.len() == 0 {
+        return state.checkers().gtz();
+    }
+    if moves == 0 {
+        return false;
+    }
+
+    replies.iter().all(|&reply| {
+        board.do_move(state, reply).unwrap();
+        let mated = mates_within(board, state, moves, checks_only);
+        board.undo_move(state, reply);
+        mated
+    })
+}
+
+/// Whether the side to move can mate within `moves` moves
+fn mates_within(board: &mut Board, state: &mut State, moves: usize, checks_only: bool) -> bool {
+    movegen::generate_legal(board, state).iter().any(|&mv| {
+        board.do_move(state, mv).unwrap();
+        let mated =
+            is_allowed(state, moves, checks_only) && is_lost(board, state, moves - 1, checks_only);
+        board.undo_move(state, mv);
+        mated
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::move_new;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    fn solve_fen(fen: &str, moves: usize, checks_only: bool) -> Solution {
+        INIT.call_once(initialize_bitboards);
+        let mut s = State::new();
+        let b = Board::new(fen, &mut s).unwrap();
+        solve(&b, &s, moves, checks_only)
+    }
+
+    fn keys(solution: &Solution) -> Vec<Move> {
+        solution.keys.iter().map(|k| k.mv).collect()
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let solution = solve_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1, false);
+        assert!(solution.is_sound());
+        assert_eq!(keys(&solution), [move_new!("a1a8")]);
+        assert!(solution.keys[0].defences.is_empty());
+
+        let solution = solve_fen("6k1/5ppp/8/8/8/8/8/RR4K1 w - - 0 1", 1, false);
+        assert!(solution.is_proven() && !solution.is_sound());
+        assert_eq!(solution.cooks().len(), 1);
+
+        // Stalemate is not mate
+        let solution = solve_fen("k7/8/1Q6/8/8/8/8/7K w - - 0 1", 1, false);
+        assert!(!solution.is_proven());
+    }
+
+    #[test]
+    fn mate_in_two() {
+        const FEN: &str = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        assert!(!solve_fen(FEN, 1, false).is_proven());
+
+        let solution = solve_fen(FEN, 2, false);
+        let mut found = keys(&solution);
+        found.sort_by_key(|m| m.to_string());
+        assert_eq!(found, [move_new!("c6b6"), move_new!("c6c7")]);
+        assert_eq!(solution.cooks().len(), 1);
+
+        // Each key has a single defence, with a single mate after it
+        for key in &solution.keys {
+            assert_eq!(key.defences.len(), 1);
+            assert_eq!(key.defences[0].continuations.len(), 1);
+        }
+        assert!(solution.duals().is_empty());
+
+        // Neither key gives check
+        assert!(!solve_fen(FEN, 2, true).is_proven());
+    }
+
+    #[test]
+    fn short_mates_are_not_duals() {
+        // After 1. Kb6 Kb8 there is mate in one, so the many waiting moves
+        // that would also mate by the third move don't count
+        let solution = solve_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1", 3, false);
+        let key = solution
+            .keys
+            .iter()
+            .find(|k| k.mv == move_new!("c6b6"))
+            .unwrap();
+        assert_eq!(key.defences.len(), 1);
+        let mates: Vec<_> = key.defences[0].continuations.iter().map(|l| l.mv).collect();
+        assert_eq!(mates, [move_new!("h1h8")]);
+        assert!(!solution.duals().iter().any(|d| d[0] == move_new!("c6b6")));
+    }
+
+    #[test]
+    fn duals_and_text() {
+        // After 1. Kb6 Kb8, both rooks mate on the eighth rank
+        const FEN: &str = "k7/8/2K5/8/8/8/8/6RR w - - 0 1";
+        let solution = solve_fen(FEN, 2, false);
+        assert!(solution.keys.iter().any(|k| k.mv == move_new!("c6b6")));
+        assert!(solution
+            .duals()
+            .contains(&vec![move_new!("c6b6"), move_new!("a8b8")]));
+
+        let solution = solve_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1, false);
+        let mut s = State::new();
+        let b = Board::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &mut s).unwrap();
+        assert_eq!(solution.to_text(&b, &s), "1. Ra8#!\n");
+
+        let solution = solve_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1", 2, false);
+        let mut s = State::new();
+        let b = Board::new("k7/8/2K5/8/8/8/8/7R w - - 0 1", &mut s).unwrap();
+        let text = solution.to_text(&b, &s);
+        assert!(
+            text.contains("1. Kb6!\n  1... Kb8\n    2. Rh8#\n"),
+            "{text}"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::prelude::*;
+
+use PieceType::*;
+
+/// The order pieces are listed in a material signature, strongest first
+pub(crate) const SIGNATURE_ORDER: [PieceType; PieceType::COUNT] =
+    [King, Queen, Rook, Bishop, Knight, Pawn];
+
+impl Board {
+    /// A key that is equal for two positions exactly when they have the same
+    /// material, for quickly dispatching on it.
+    ///
+    /// Every ([`Color`], [`PieceType`]) pair has its count in its own four bits.
+    /// Counts above fifteen, which only lenient positions can have, are
+    /// counted as fifteen, so such positions may share a key.
+    pub fn material_key(&self) -> u64 {
+        let mut key = 0;
+        for color in [Color::White, Color::Black] {
+            for pt in SIGNATURE_ORDER {
+                let count = self.piece_count(color, pt).clamp(0, 15) as u64;
+                key |= count << material_shift(color, pt);
+            }
+        }
+
+        key
+    }
+
+    /// The material on the board as text, white first and strongest piece
+    /// first, such as `KQPkr`.
+    pub fn material_signature(&self) -> String {
+        let mut sig = String::with_capacity(32);
+        for color in [Color::White, Color::Black] {
+            for pt in SIGNATURE_ORDER {
+                let p = (pt + color).to_string();
+                sig.extend(std::iter::repeat_n(p, self.piece_count(color, pt) as usize));
+            }
+        }
+
+        sig
+    }
+
+    /// The [`Board::material_key`] any position with the material in `signature`
+    /// has. Pieces may be given in any order, but there may not be more than
+    /// fifteen of one kind.
+    pub fn material_key_of(signature: &str) -> Option<u64> {
+        let mut counts = [0u64; PieceType::COUNT * Color::COUNT];
+        for c in signature.chars() {
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let pt = match c.to_ascii_lowercase() {
+                'p' => Pawn,
+                'n' => Knight,
+                'b' => Bishop,
+                'r' => Rook,
+                'q' => Queen,
+                'k' => King,
+                _ => return None,
+            };
+
+            let count = &mut counts[PieceType::COUNT * color.to_usize() + pt.to_usize()];
+            *count += 1;
+            if *count > 15 {
+                return None;
+            }
+        }
+
+        let mut key = 0;
+        for color in [Color::White, Color::Black] {
+            for pt in SIGNATURE_ORDER {
+                let count = counts[PieceType::COUNT * color.to_usize() + pt.to_usize()];
+                key |= count << material_shift(color, pt);
+            }
+        }
+
+        Some(key)
+    }
+}
+
+const fn material_shift(color: Color, pt: PieceType) -> usize {
+    4 * (PieceType::COUNT * color.to_usize() + pt.to_usize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::move_new;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn counts_follow_moves() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let mut b = Board::new(Board::STARTPOS, &mut s).unwrap();
+        assert_eq!(b.piece_count(Color::White, Pawn), 8);
+        assert_eq!(b.piece_count(Color::Black, Knight), 2);
+        assert_eq!(b.material_signature(), "KQRRBBNNPPPPPPPPkqrrbbnnpppppppp");
+
+        // 1. e4 d5 2. exd5 Qxd5
+        let moves = [
+            move_new!("e2e4"),
+            move_new!("d7d5"),
+            move_new!("e4d5"),
+            move_new!("d8d5"),
+        ];
+        b.apply_moves(&mut s, &moves).unwrap();
+        assert_eq!(b.piece_count(Color::White, Pawn), 7);
+        assert_eq!(b.piece_count(Color::Black, Pawn), 7);
+
+        for &m in moves.iter().rev() {
+            b.undo_move(&mut s, m);
+        }
+        assert_eq!(b.piece_count(Color::White, Pawn), 8);
+        assert_eq!(b.piece_count(Color::Black, Pawn), 8);
+    }
+
+    #[test]
+    fn promotions() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let mut b = Board::new("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", &mut s).unwrap();
+        assert_eq!(b.material_signature(), "KPkr");
+
+        let m = move_new!("a7b8q");
+        b.do_move(&mut s, m).unwrap();
+        assert_eq!(b.material_signature(), "KQk");
+        assert_eq!(Board::material_key_of("kKQ"), Some(b.material_key()));
+
+        b.undo_move(&mut s, m);
+        assert_eq!(b.material_signature(), "KPkr");
+        assert_eq!(Board::material_key_of("KPkr"), Some(b.material_key()));
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(Board::material_key_of(""), Some(0));
+        assert_eq!(Board::material_key_of("KQx"), None);
+        assert_ne!(Board::material_key_of("KQk"), Board::material_key_of("Kkq"));
+        assert_ne!(Board::material_key_of("KRk"), Board::material_key_of("KBk"));
+    }
+
+    #[test]
+    fn too_many_pieces() {
+        INIT.call_once(initialize_bitboards);
+
+        // Sixteen knights don't spill over into the next count
+        let mut s = State::new();
+        let b = Board::new_lenient("K6k/8/8/8/8/8/NNNNNNNN/NNNNNNNN w - - 0 1", &mut s).unwrap();
+        let fifteen = format!("K{}k", "N".repeat(15));
+        assert_eq!(Some(b.material_key()), Board::material_key_of(&fifteen));
+    }
+}
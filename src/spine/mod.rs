@@ -1,25 +1,44 @@
 mod board;
 mod chess_move;
 mod color;
+mod fen;
 mod file;
 mod magic;
+mod material;
 mod movelist;
 mod piece;
 mod prng;
 mod rank;
+mod retract;
+mod san;
+mod score;
 mod square;
+mod transform;
+mod validate;
 
 pub mod bitboard;
+pub mod book;
+pub mod endgame;
+pub mod mate;
 pub mod movegen;
+pub mod movepick;
+pub mod nnue;
 pub mod perft;
+pub mod pgn;
 pub mod piece_attacks;
+pub mod retro;
+pub mod timeman;
+pub mod tournament;
 
 pub use bitboard::{Bitboard, ShiftDir};
-pub use board::{Board, CastleRights, State};
+pub use board::{Board, BoardBuilder, BoardCreationError, CastleRight, CastleRights, State};
 pub use chess_move::{Move, MoveFlag};
 pub use color::Color;
+pub use fen::{FenError, FenErrorKind};
 pub use file::File;
 pub use movelist::Movelist;
 pub use piece::{Piece, PieceType};
 pub use rank::Rank;
+pub use score::Score;
 pub use square::Square;
+pub use validate::PositionError;
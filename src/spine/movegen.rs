@@ -188,3 +188,273 @@ pub fn generate_legal(board: &Board, state: &State) -> Movelist {
 
     list
 }
+
+/// A move taken back: the [`Move`] as it was played in the position before,
+/// and the piece it captured, if any
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unmove {
+    pub mv: Move,
+    pub uncapture: Option<PieceType>,
+}
+
+const UNCAPTURES: [Option<PieceType>; 6] = [
+    None,
+    Some(Pawn),
+    Some(Knight),
+    Some(Bishop),
+    Some(Rook),
+    Some(Queen),
+];
+
+/// The position before the last move, as the change from the current one:
+/// the `kind` of piece on `to` goes back to `from` as a `prev_kind` (which
+/// differs for promotions), and the `uncapture`d piece is put back.
+struct Retraction<'a> {
+    board: &'a Board,
+    from: Square,
+    to: Square,
+    kind: PieceType,
+    prev_kind: PieceType,
+    uncapture: Option<(PieceType, Square)>,
+}
+
+impl Retraction<'_> {
+    fn pieces(&self, color: Color, pt: PieceType) -> Bitboard {
+        let mut bb = self.board.spec(color, pt);
+        if color != self.board.to_move() {
+            if pt == self.kind {
+                bb ^= self.to;
+            }
+            if pt == self.prev_kind {
+                bb |= self.from;
+            }
+        } else if let Some((_, s)) = self.uncapture.filter(|&(k, _)| k == pt) {
+            bb |= s;
+        }
+        bb
+    }
+
+    fn attackers(&self, square: Square, color: Color) -> Bitboard {
+        let mut occ = (self.board.all() ^ self.to) | self.from;
+        if let Some((_, s)) = self.uncapture {
+            occ |= s;
+        }
+
+        let queens = self.pieces(color, Queen);
+        (pawn_attacks(square, !color) & self.pieces(color, Pawn))
+            | (knight_attacks(square) & self.pieces(color, Knight))
+            | (bishop_attacks(square, occ) & (self.pieces(color, Bishop) | queens))
+            | (rook_attacks(square, occ) & (self.pieces(color, Rook) | queens))
+            | (king_attacks(square) & self.pieces(color, King))
+    }
+
+    /// Whether the position before the move could have happened: the side
+    /// not to move in it can't be in check, and the side to move can't be in
+    /// check more than twice.
+    fn is_legal(&self) -> bool {
+        let us = self.board.to_move();
+        let their_king = self.pieces(!us, King).lsb();
+
+        !self.attackers(self.board.king(us), !us).gtz()
+            && self.attackers(their_king, us).popcount() <= 2
+    }
+}
+
+fn push_unmove(list: &mut Vec<Unmove>, r: Retraction, mv: Move) {
+    if r.is_legal() {
+        list.push(Unmove {
+            mv,
+            uncapture: r.uncapture.map(|(k, _)| k),
+        });
+    }
+}
+
+/// Generates the moves that could have been played to reach the position.
+///
+/// Captured pieces come back as any piece but a king, and promoted pieces may
+/// turn back into pawns. Castling rights, the en passant square and the
+/// half-move clock of the position before can't always be told from this
+/// one; [`Board::retract`] says what is assumed for them.
+pub fn generate_unmoves(board: &Board, state: &State) -> Vec<Unmove> {
+    unmoves(board, state, false)
+}
+
+/// Like [`generate_unmoves`], without the uncaptures, unpromotions and
+/// castling, so that the position before has the same material and rights
+pub(crate) fn generate_quiet_unmoves(board: &Board, state: &State) -> Vec<Unmove> {
+    unmoves(board, state, true)
+}
+
+fn unmoves(board: &Board, state: &State, quiet: bool) -> Vec<Unmove> {
+    let mut list = Vec::new();
+    let uncaptures = if quiet {
+        &UNCAPTURES[..1]
+    } else {
+        &UNCAPTURES[..]
+    };
+
+    let us = board.to_move();
+    let them = !us;
+    let empty = !board.all();
+
+    let retraction = |from, to, kind, prev_kind, uncapture: Option<PieceType>| Retraction {
+        board,
+        from,
+        to,
+        kind,
+        prev_kind,
+        uncapture: uncapture.map(|k| (k, to)),
+    };
+
+    if let Some(ep) = state.en_passant() {
+        // Only a double step gives the chance to take en passant
+        let (from, to) = (ep + Backward(them), ep + Forward(them));
+        let r = retraction(from, to, Pawn, Pawn, None);
+        push_unmove(&mut list, r, move_new!(from, to));
+        return list;
+    }
+
+    // A king or rook that can still castle hasn't moved
+    let mut fixed = Bitboard::ZERO;
+    for right in state.castle_rights().rights_for(them).into_iter().flatten() {
+        fixed |= Bitboard::from([right.king_from, right.rook_from]);
+    }
+
+    for to in board.color(them).and_not(fixed) {
+        let kind = board.get_piece(to).unwrap().kind();
+        let on_back_rank = matches!(to.rank(), Rank::One | Rank::Eight);
+
+        if kind == Pawn {
+            if to.rank().relative_to(them) <= Rank::Two {
+                continue;
+            }
+
+            let back = to + Backward(them);
+            if (empty & back).gtz() {
+                push_unmove(
+                    &mut list,
+                    retraction(back, to, Pawn, Pawn, None),
+                    move_new!(back, to),
+                );
+
+                // That would have left an en passant capture
+                let start = back + Backward(them);
+                if to.rank() == Rank::Four.relative_to(them)
+                    && (empty & start).gtz()
+                    && !(pawn_attacks(back, them) & board.spec(us, Pawn)).gtz()
+                {
+                    push_unmove(
+                        &mut list,
+                        retraction(start, to, Pawn, Pawn, None),
+                        move_new!(start, to),
+                    );
+                }
+            }
+
+            for from in pawn_attacks(to, us) & empty {
+                for &uncapture in uncaptures.iter().skip(1) {
+                    let r = retraction(from, to, Pawn, Pawn, uncapture);
+                    push_unmove(&mut list, r, move_new!(from, to));
+                }
+
+                // En passant: our pawn had stepped past `to`, from the square
+                // in front of it
+                let (passed, start) = (to + Backward(them), to + Forward(them));
+                let ep = !quiet
+                    && to.rank() == Rank::Six.relative_to(them)
+                    && (empty & passed).gtz()
+                    && (empty & start).gtz();
+                if ep {
+                    let r = Retraction {
+                        uncapture: Some((Pawn, passed)),
+                        ..retraction(from, to, Pawn, Pawn, None)
+                    };
+                    push_unmove(&mut list, r, move_new!(from, to, MoveFlag::EnPassant));
+                }
+            }
+            continue;
+        }
+
+        let origins = match kind {
+            Knight => knight_attacks(to),
+            Bishop => bishop_attacks(to, board.all()),
+            Rook => rook_attacks(to, board.all()),
+            Queen => piece_attacks::queen_attacks(to, board.all()),
+            _ => king_attacks(to),
+        } & empty;
+
+        for from in origins {
+            for &uncapture in uncaptures {
+                if uncapture == Some(Pawn) && on_back_rank {
+                    continue;
+                }
+                let r = retraction(from, to, kind, kind, uncapture);
+                push_unmove(&mut list, r, move_new!(from, to));
+            }
+        }
+
+        if quiet {
+            continue;
+        }
+
+        if kind == King {
+            generate_uncastles(&mut list, board, state, to);
+            continue;
+        }
+        if to.rank() != Rank::Eight.relative_to(them) {
+            continue;
+        }
+
+        let promotion = |from| Move::new(from, to, MoveFlag::Promotion, kind);
+        let back = to + Backward(them);
+        if (empty & back).gtz() {
+            let r = retraction(back, to, kind, Pawn, None);
+            push_unmove(&mut list, r, promotion(back));
+        }
+        for from in pawn_attacks(to, us) & empty {
+            for uncapture in UNCAPTURES.into_iter().skip(2) {
+                let r = retraction(from, to, kind, Pawn, uncapture);
+                push_unmove(&mut list, r, promotion(from));
+            }
+        }
+    }
+
+    list
+}
+
+fn generate_uncastles(list: &mut Vec<Unmove>, board: &Board, state: &State, king: Square) {
+    let them = !board.to_move();
+    if !state
+        .castle_rights()
+        .rights_for(them)
+        .iter()
+        .all(Option::is_none)
+    {
+        return;
+    }
+
+    for short in [true, false] {
+        let right = CastleRight::standard(them, short);
+        let squares = Bitboard::between::<false>(right.king_from, right.rook_from)
+            | right.king_from
+            | right.rook_from;
+
+        if king != right.king_to
+            || board.get_piece(right.rook_to) != Some(Rook + them)
+            || board.all() & squares != Bitboard::from([right.king_to, right.rook_to])
+        {
+            continue;
+        }
+
+        // Castling has more conditions than any other move, so try it out
+        let unmove = Unmove {
+            mv: move_new!(right.king_from, right.king_to, MoveFlag::Castle),
+            uncapture: None,
+        };
+        if let Ok((b, s)) = board.retract(state, unmove) {
+            if generate_legal(&b, &s).iter().any(|&m| m == unmove.mv) {
+                list.push(unmove);
+            }
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Ordering moves so that a search tries the likely best ones first.
+//!
+//! [`MovePicker`] hands out the legal moves of a position in stages: the
+//! transposition table move, captures that don't lose material (most
+//! valuable victim first, least valuable attacker breaking ties), the killer
+//! moves, the countermove, the remaining quiet moves by their [`History`]
+//! score, and finally the captures that [`see`] says lose material.
+
+use crate::movegen;
+use crate::prelude::*;
+
+use PieceType::*;
+
+/// The values [`see`] trades pieces at. The king is worth more than
+/// everything else put together, so it is never given up.
+const SEE_VALUES: [i32; PieceType::COUNT] = [100, 300, 300, 500, 900, 20_000];
+
+/// [`Piece::to_usize`] leaves a gap between the colours
+const PIECE_SLOTS: usize = 16;
+
+/// History scores stay within this, in either direction
+const MAX_HISTORY: i32 = 16_384;
+
+/// The static exchange evaluation of `mv`: the material the side to move
+/// comes out with if both sides keep capturing on the destination square
+/// with their least valuable piece, and either may stop when it suits them.
+///
+/// Pins and checks are not taken into account.
+pub fn see(board: &Board, mv: Move) -> i32 {
+    let (from, to) = (mv.from_square(), mv.to_square());
+    let mut occupied = board.all() ^ from;
+    let mut on_square = board.get_piece(from).map_or(Pawn, |p| p.kind());
+
+    let mut gain = [0; 32];
+    match mv.flag() {
+        MoveFlag::Castle => return 0,
+        MoveFlag::EnPassant => {
+            occupied ^= Square::build(to.file(), from.rank());
+            gain[0] = SEE_VALUES[Pawn.to_usize()];
+        }
+        _ => {
+            gain[0] = board
+                .get_piece(to)
+                .map_or(0, |p| SEE_VALUES[p.kind().to_usize()])
+        }
+    }
+    if mv.flag() == MoveFlag::Promotion {
+        on_square = mv.promotion_type();
+        gain[0] += SEE_VALUES[on_square.to_usize()] - SEE_VALUES[Pawn.to_usize()];
+    }
+
+    let mut side = !board.to_move();
+    let mut depth = 0;
+    loop {
+        let attackers = board.attacks_to_bits(to, occupied) & occupied;
+        let ours = attackers & board.color(side);
+        let Some(pt) = [Pawn, Knight, Bishop, Rook, Queen, King]
+            .into_iter()
+            .find(|&pt| (ours & board.piece_type(pt)).gtz())
+        else {
+            break;
+        };
+        // The king can only take last
+        if pt == King && (attackers & board.color(!side)).gtz() {
+            break;
+        }
+
+        depth += 1;
+        gain[depth] = SEE_VALUES[on_square.to_usize()] - gain[depth - 1];
+        occupied ^= (ours & board.piece_type(pt)).lsb();
+        on_square = pt;
+        side = !side;
+    }
+
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+
+    gain[0]
+}
+
+fn is_noisy(board: &Board, mv: Move) -> bool {
+    match mv.flag() {
+        MoveFlag::EnPassant | MoveFlag::Promotion => true,
+        MoveFlag::Castle => false,
+        MoveFlag::Normal => board.get_piece(mv.to_square()).is_some(),
+    }
+}
+
+/// What a search has learned about quiet moves so far, which
+/// [`MovePicker`] orders them by.
+///
+/// Every table is indexed by the [`Piece`] that moves and the [`Square`] it
+/// moves to, so the same move by a different piece is counted separately.
+#[derive(Debug, Clone)]
+pub struct History {
+    butterfly: [[i32; Square::COUNT]; PIECE_SLOTS],
+    countermoves: [[Move; Square::COUNT]; PIECE_SLOTS],
+    killers: Vec<[Move; 2]>,
+}
+
+impl History {
+    /// Empty tables, which order quiet moves as they were generated
+    pub fn new() -> Self {
+        Self {
+            butterfly: [[0; Square::COUNT]; PIECE_SLOTS],
+            countermoves: [[Move::NULL; Square::COUNT]; PIECE_SLOTS],
+            killers: Vec::new(),
+        }
+    }
+
+    /// Forget everything, as before a new game
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// The history score of `piece` moving to `to`
+    pub fn score(&self, piece: Piece, to: Square) -> i32 {
+        self.butterfly[piece.to_usize()][to.to_usize()]
+    }
+
+    /// The two most recent quiet moves that caused a cutoff at `ply`, newest
+    /// first. Missing ones are [`Move::NULL`].
+    pub fn killers(&self, ply: usize) -> [Move; 2] {
+        self.killers.get(ply).copied().unwrap_or([Move::NULL; 2])
+    }
+
+    /// The quiet move that last refuted `prev`, the move played to reach
+    /// `board`, or [`Move::NULL`]
+    pub fn countermove(&self, board: &Board, prev: Move) -> Move {
+        match board.get_piece(prev.to_square()) {
+            Some(p) if prev != Move::NULL => {
+                self.countermoves[p.to_usize()][prev.to_square().to_usize()]
+            }
+            _ => Move::NULL,
+        }
+    }
+
+    /// Record that the quiet move `best` caused a cutoff in `board` at `ply`,
+    /// searched to `depth`, after the quiet moves in `tried` did not.
+    /// `prev` is the move played to reach `board`.
+    pub fn update(
+        &mut self,
+        board: &Board,
+        ply: usize,
+        depth: i32,
+        best: Move,
+        prev: Option<Move>,
+        tried: &[Move],
+    ) {
+        let bonus = (depth * depth).min(MAX_HISTORY / 8);
+        self.add(board, best, bonus);
+        for &mv in tried.iter().filter(|&&mv| mv != best) {
+            self.add(board, mv, -bonus);
+        }
+
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [Move::NULL; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != best {
+            killers[1] = killers[0];
+            killers[0] = best;
+        }
+
+        if let Some(prev) = prev {
+            if let Some(p) = board.get_piece(prev.to_square()) {
+                self.countermoves[p.to_usize()][prev.to_square().to_usize()] = best;
+            }
+        }
+    }
+
+    /// Move the score towards the bound by `bonus`, by less the closer it
+    /// already is
+    fn add(&mut self, board: &Board, mv: Move, bonus: i32) {
+        let Some(piece) = board.get_piece(mv.from_square()) else {
+            return;
+        };
+        let entry = &mut self.butterfly[piece.to_usize()][mv.to_square().to_usize()];
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The groups of moves [`MovePicker`] hands out, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    TtMove,
+    GoodCaptures,
+    Killers,
+    Countermove,
+    Quiets,
+    BadCaptures,
+}
+
+/// An iterator over the legal moves of a position, best looking first.
+///
+/// Captures and promotions count as captures; every other move is quiet.
+/// Each move comes out once, in the first stage it belongs to.
+#[derive(Debug)]
+pub struct MovePicker {
+    moves: Vec<(Move, Stage, i32)>,
+    stage: Stage,
+}
+
+impl MovePicker {
+    /// Order the moves of `board`, with `tt_move` first if it is legal.
+    /// `ply` picks the killer moves to use, and `prev` is the move played to
+    /// reach `board`, for the countermove.
+    pub fn new(
+        board: &Board,
+        state: &State,
+        history: &History,
+        tt_move: Option<Move>,
+        ply: usize,
+        prev: Option<Move>,
+    ) -> Self {
+        let killers = history.killers(ply);
+        let countermove = prev.map_or(Move::NULL, |prev| history.countermove(board, prev));
+
+        let moves = movegen::generate_legal(board, state)
+            .iter()
+            .map(|&mv| {
+                let (stage, score) = if Some(mv) == tt_move {
+                    (Stage::TtMove, 0)
+                } else if is_noisy(board, mv) {
+                    Self::capture_order(board, mv)
+                } else if mv == killers[0] {
+                    (Stage::Killers, 1)
+                } else if mv == killers[1] {
+                    (Stage::Killers, 0)
+                } else if mv == countermove {
+                    (Stage::Countermove, 0)
+                } else {
+                    let piece = board.get_piece(mv.from_square()).unwrap();
+                    (Stage::Quiets, history.score(piece, mv.to_square()))
+                };
+                (mv, stage, score)
+            })
+            .collect();
+
+        Self {
+            moves,
+            stage: Stage::TtMove,
+        }
+    }
+
+    fn capture_order(board: &Board, mv: Move) -> (Stage, i32) {
+        let exchange = see(board, mv);
+        if exchange < 0 {
+            return (Stage::BadCaptures, exchange);
+        }
+
+        let victim = match mv.flag() {
+            MoveFlag::EnPassant => SEE_VALUES[Pawn.to_usize()],
+            _ => board
+                .get_piece(mv.to_square())
+                .map_or(0, |p| SEE_VALUES[p.kind().to_usize()]),
+        };
+        let promotion = match mv.flag() {
+            MoveFlag::Promotion => SEE_VALUES[mv.promotion_type().to_usize()],
+            _ => 0,
+        };
+        let attacker = board.get_piece(mv.from_square()).unwrap().kind();
+
+        (
+            Stage::GoodCaptures,
+            16 * (victim + promotion) - attacker.to_usize() as i32,
+        )
+    }
+
+    /// The stage of the move last handed out
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let (i, &(mv, stage, _)) = self
+            .moves
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &(_, stage, score))| (stage, -score))?;
+
+        let _ = self.moves.swap_remove(i);
+        self.stage = stage;
+        Some(mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::move_new;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    const FEN: &str = "4k3/8/2p5/1r1p4/4P3/2N5/8/3QK3 w - - 0 1";
+
+    #[test]
+    fn exchanges() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let b = Board::new(FEN, &mut s).unwrap();
+        assert_eq!(see(&b, move_new!("c3b5")), 200);
+        assert_eq!(see(&b, move_new!("e4d5")), 100);
+        assert_eq!(see(&b, move_new!("c3d5")), -100);
+        assert_eq!(see(&b, move_new!("d1d5")), -700);
+        assert_eq!(see(&b, move_new!("d1d4")), 0);
+    }
+
+    #[test]
+    fn staged_order() {
+        INIT.call_once(initialize_bitboards);
+
+        let mut s = State::new();
+        let b = Board::new(FEN, &mut s).unwrap();
+
+        let mut history = History::new();
+        let prev = move_new!("d8e8");
+        history.update(&b, 0, 4, move_new!("e1f1"), Some(prev), &[]);
+        history.update(&b, 0, 4, move_new!("d1g4"), None, &[]);
+        history.update(&b, 0, 4, move_new!("d1h5"), None, &[]);
+        history.update(&b, 5, 8, move_new!("d1a4"), None, &[move_new!("d1d2")]);
+
+        let tt = move_new!("c3d5");
+        let mut picker = MovePicker::new(&b, &s, &history, Some(tt), 0, Some(prev));
+        let mut expect = |stage, mv: Move| {
+            assert_eq!(picker.next(), Some(mv));
+            assert_eq!(picker.stage(), stage, "{mv}");
+        };
+        expect(Stage::TtMove, move_new!("c3d5"));
+        expect(Stage::GoodCaptures, move_new!("c3b5"));
+        expect(Stage::GoodCaptures, move_new!("e4d5"));
+        expect(Stage::Killers, move_new!("d1h5"));
+        expect(Stage::Killers, move_new!("d1g4"));
+        expect(Stage::Countermove, move_new!("e1f1"));
+        expect(Stage::Quiets, move_new!("d1a4"));
+
+        let rest: Vec<_> = picker.by_ref().collect();
+        let legal = movegen::generate_legal(&b, &s).len();
+        assert_eq!(rest.len(), legal - 7);
+        assert_eq!(rest[rest.len() - 2], move_new!("d1d2"));
+        assert_eq!(rest[rest.len() - 1], move_new!("d1d5"));
+        assert_eq!(picker.stage(), Stage::BadCaptures);
+    }
+}
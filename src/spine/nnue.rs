@@ -0,0 +1,448 @@
+//! Evaluation by an efficiently updatable neural network.
+//!
+//! The inputs are HalfKP features: for each side, every piece other than a
+//! king, seen from that side and paired with that side's king square. They
+//! feed a hidden layer per side (the accumulator), which is kept up to date
+//! move by move from the pieces [`Board::do_move`] changed, and only rebuilt
+//! for a side when its king moves. Both halves go through a clipped ReLU
+//! into a single output, the side to move's half first.
+//!
+//! The hot loops have an AVX2 version, picked at runtime, and a scalar one
+//! that serves as the reference.
+
+use crate::prelude::*;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"CNUE";
+
+/// Pieces other than kings, in both colours
+const PIECE_KINDS: usize = 10;
+const FEATURES: usize = Square::COUNT * PIECE_KINDS * Square::COUNT;
+
+/// The quantisation of the hidden layer and of the output weights
+const QA: i32 = 255;
+const QB: i32 = 64;
+/// Centipawns per unit of network output
+const SCALE: i32 = 400;
+
+/// The weights of a network, as loaded from a file.
+///
+/// The file starts with `CNUE` and the hidden layer size as a little-endian
+/// `u32`, which must be a multiple of 16. Then come, all little-endian `i16`s
+/// unless noted: the hidden biases, the hidden weights feature by feature,
+/// the output weights for the side to move and then the other side, and the
+/// output bias as an `i32`.
+#[derive(Clone)]
+pub struct Network {
+    hidden: usize,
+    ft_bias: Vec<i16>,
+    ft_weights: Vec<i16>,
+    out_weights: Vec<i16>,
+    out_bias: i32,
+}
+
+impl Network {
+    /// The number of neurons in each side's half of the hidden layer
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// Evaluate `board` from scratch, from the point of view of the side to
+    /// move
+    pub fn evaluate(&self, board: &Board) -> Score {
+        Evaluator::new(self, board).evaluate(board)
+    }
+
+    /// Write the network in the format [`Network::read`] expects
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.hidden as u32).to_le_bytes())?;
+        for part in [&self.ft_bias, &self.ft_weights, &self.out_weights] {
+            for w in part.iter() {
+                writer.write_all(&w.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&self.out_bias.to_le_bytes())?;
+        writer.flush()
+    }
+
+    /// Read a network written by [`Network::write`]
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a network"));
+        }
+        let hidden = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+        if hidden == 0 || !hidden.is_multiple_of(16) {
+            return Err(invalid("hidden layer size must be a multiple of 16"));
+        }
+
+        let mut read_i16s = |len: usize| -> io::Result<Vec<i16>> {
+            let mut bytes = vec![0; 2 * len];
+            reader.read_exact(&mut bytes)?;
+            Ok(bytes
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                .collect())
+        };
+        let ft_bias = read_i16s(hidden)?;
+        let ft_weights = read_i16s(FEATURES * hidden)?;
+        let out_weights = read_i16s(2 * hidden)?;
+
+        let mut bias = [0; 4];
+        reader.read_exact(&mut bias)?;
+
+        Ok(Self {
+            hidden,
+            ft_bias,
+            ft_weights,
+            out_weights,
+            out_bias: i32::from_le_bytes(bias),
+        })
+    }
+
+    /// Save the network to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Load a network saved by [`Network::save`]
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    fn column(&self, feature: usize) -> &[i16] {
+        &self.ft_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Network")
+            .field("hidden", &self.hidden)
+            .finish()
+    }
+}
+
+/// The input that `piece` on `square` sets for `perspective`, whose king is
+/// on `king`. Everything is flipped for black, so both sides see the board
+/// from their own first rank.
+fn feature(perspective: Color, king: Square, piece: Piece, square: Square) -> usize {
+    debug_assert!(piece.kind() != PieceType::King);
+    let kind = 2 * piece.kind().to_usize() + (piece.color() != perspective) as usize;
+    let king = king.relative_to(perspective).to_usize();
+    (king * PIECE_KINDS + kind) * Square::COUNT + square.relative_to(perspective).to_usize()
+}
+
+/// The hidden layer of a [`Network`] for a line of play, kept up to date as
+/// moves are made and taken back.
+///
+/// Call [`Evaluator::push`] after every [`Board::do_move`] (or
+/// [`Board::do_null_move`]) and [`Evaluator::pop`] after undoing it.
+#[derive(Debug, Clone)]
+pub struct Evaluator<'a> {
+    net: &'a Network,
+    /// One accumulator per ply, white's half then black's
+    stack: Vec<i16>,
+}
+
+impl<'a> Evaluator<'a> {
+    /// Start from `board`, building its accumulator from scratch
+    pub fn new(net: &'a Network, board: &Board) -> Self {
+        let mut ev = Self {
+            net,
+            stack: vec![0; 2 * net.hidden],
+        };
+        for color in [Color::White, Color::Black] {
+            ev.refresh(board, color);
+        }
+        ev
+    }
+
+    fn half(&mut self, color: Color) -> &mut [i16] {
+        let h = self.net.hidden;
+        let top = self.stack.len() - 2 * h;
+        let start = top + color.to_usize() * h;
+        &mut self.stack[start..start + h]
+    }
+
+    fn refresh(&mut self, board: &Board, perspective: Color) {
+        let net = self.net;
+        let king = board.king(perspective);
+        let acc = self.half(perspective);
+        acc.copy_from_slice(&net.ft_bias);
+
+        for square in board.all() ^ board.piece_type(PieceType::King) {
+            let piece = board.get_piece(square).unwrap();
+            simd::add(acc, net.column(feature(perspective, king, piece, square)));
+        }
+    }
+
+    /// Bring the accumulator up to date with the move that led to `board`,
+    /// whose state is `state`
+    pub fn push(&mut self, board: &Board, state: &State) {
+        let h = self.net.hidden;
+        let top = self.stack.len() - 2 * h;
+        self.stack.extend_from_within(top..);
+
+        let net = self.net;
+        for perspective in [Color::White, Color::Black] {
+            let own_king = PieceType::King + perspective;
+            if state.dirty_pieces().any(|d| d.piece == own_king) {
+                self.refresh(board, perspective);
+                continue;
+            }
+
+            let king = board.king(perspective);
+            let acc = self.half(perspective);
+            for d in state.dirty_pieces() {
+                if d.piece.kind() == PieceType::King {
+                    continue;
+                }
+                if let Some(from) = d.from {
+                    simd::sub(acc, net.column(feature(perspective, king, d.piece, from)));
+                }
+                if let Some(to) = d.to {
+                    simd::add(acc, net.column(feature(perspective, king, d.piece, to)));
+                }
+            }
+        }
+    }
+
+    /// Go back to the accumulator before the last [`Evaluator::push`]
+    pub fn pop(&mut self) {
+        debug_assert!(self.stack.len() > 2 * self.net.hidden);
+        self.stack.truncate(self.stack.len() - 2 * self.net.hidden);
+    }
+
+    /// Evaluate `board`, the position after the moves pushed so far, from
+    /// the point of view of the side to move
+    pub fn evaluate(&self, board: &Board) -> Score {
+        let h = self.net.hidden;
+        let top = &self.stack[self.stack.len() - 2 * h..];
+        let (white, black) = top.split_at(h);
+        let (us, them) = match board.to_move() {
+            Color::White => (white, black),
+            Color::Black => (black, white),
+        };
+
+        let (w_us, w_them) = self.net.out_weights.split_at(h);
+        let sum = simd::output(us, w_us) + simd::output(them, w_them);
+        Score::new((sum + self.net.out_bias) * SCALE / (QA * QB))
+    }
+}
+
+/// The loops over the hidden layer
+mod simd {
+    pub(super) fn add(acc: &mut [i16], column: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        if std::is_x86_feature_detected!("avx2") {
+            // Safety: AVX2 is available
+            return unsafe { avx2::add(acc, column) };
+        }
+        scalar::add(acc, column)
+    }
+
+    pub(super) fn sub(acc: &mut [i16], column: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        if std::is_x86_feature_detected!("avx2") {
+            // Safety: AVX2 is available
+            return unsafe { avx2::sub(acc, column) };
+        }
+        scalar::sub(acc, column)
+    }
+
+    /// The dot product of the clipped accumulator half with `weights`
+    pub(super) fn output(acc: &[i16], weights: &[i16]) -> i32 {
+        #[cfg(target_arch = "x86_64")]
+        if std::is_x86_feature_detected!("avx2") {
+            // Safety: AVX2 is available
+            return unsafe { avx2::output(acc, weights) };
+        }
+        scalar::output(acc, weights)
+    }
+
+    pub(super) mod scalar {
+        use super::super::QA;
+
+        pub(in super::super) fn add(acc: &mut [i16], column: &[i16]) {
+            for (a, &w) in acc.iter_mut().zip(column) {
+                *a = a.wrapping_add(w);
+            }
+        }
+
+        pub(in super::super) fn sub(acc: &mut [i16], column: &[i16]) {
+            for (a, &w) in acc.iter_mut().zip(column) {
+                *a = a.wrapping_sub(w);
+            }
+        }
+
+        pub(in super::super) fn output(acc: &[i16], weights: &[i16]) -> i32 {
+            acc.iter()
+                .zip(weights)
+                .map(|(&a, &w)| (a as i32).clamp(0, QA) * w as i32)
+                .sum()
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    pub(super) mod avx2 {
+        use super::super::QA;
+        use std::arch::x86_64::*;
+
+        // Every slice is a multiple of 16 long, as checked when the network
+        // is read.
+
+        #[target_feature(enable = "avx2")]
+        pub(in super::super) unsafe fn add(acc: &mut [i16], column: &[i16]) {
+            for (a, w) in acc.chunks_exact_mut(16).zip(column.chunks_exact(16)) {
+                let a_ptr = a.as_mut_ptr() as *mut __m256i;
+                let sum = _mm256_add_epi16(
+                    _mm256_loadu_si256(a_ptr),
+                    _mm256_loadu_si256(w.as_ptr() as *const __m256i),
+                );
+                _mm256_storeu_si256(a_ptr, sum);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub(in super::super) unsafe fn sub(acc: &mut [i16], column: &[i16]) {
+            for (a, w) in acc.chunks_exact_mut(16).zip(column.chunks_exact(16)) {
+                let a_ptr = a.as_mut_ptr() as *mut __m256i;
+                let diff = _mm256_sub_epi16(
+                    _mm256_loadu_si256(a_ptr),
+                    _mm256_loadu_si256(w.as_ptr() as *const __m256i),
+                );
+                _mm256_storeu_si256(a_ptr, diff);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub(in super::super) unsafe fn output(acc: &[i16], weights: &[i16]) -> i32 {
+            let zero = _mm256_setzero_si256();
+            let max = _mm256_set1_epi16(QA as i16);
+            let mut sum = _mm256_setzero_si256();
+            for (a, w) in acc.chunks_exact(16).zip(weights.chunks_exact(16)) {
+                let a = _mm256_loadu_si256(a.as_ptr() as *const __m256i);
+                let clipped = _mm256_min_epi16(_mm256_max_epi16(a, zero), max);
+                let w = _mm256_loadu_si256(w.as_ptr() as *const __m256i);
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, w));
+            }
+
+            let mut lanes = [0i32; 8];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+            lanes.iter().sum()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen;
+    use crate::spine::bitboard::initialize_bitboards;
+    use crate::spine::prng::PRNG;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    fn random_network(hidden: usize) -> Network {
+        let mut prng = PRNG::new(0x4E4E_5545);
+        let mut weights = |len: usize| -> Vec<i16> {
+            (0..len)
+                .map(|_| (prng.get::<u64>() % 129) as i16 - 64)
+                .collect()
+        };
+
+        Network {
+            hidden,
+            ft_bias: weights(hidden),
+            ft_weights: weights(FEATURES * hidden),
+            out_weights: weights(2 * hidden),
+            out_bias: 1234,
+        }
+    }
+
+    fn walk(net: &Network, ev: &mut Evaluator, board: &mut Board, state: &mut State, depth: usize) {
+        for &m in movegen::generate_legal(board, state).iter() {
+            board.do_move(state, m).unwrap();
+            ev.push(board, state);
+            assert_eq!(ev.evaluate(board), net.evaluate(board), "{m}");
+
+            if depth > 1 {
+                walk(net, ev, board, state, depth - 1);
+            }
+            board.undo_move(state, m);
+            ev.pop();
+        }
+        assert_eq!(ev.evaluate(board), net.evaluate(board));
+    }
+
+    #[test]
+    fn incremental() {
+        INIT.call_once(initialize_bitboards);
+
+        let net = random_network(32);
+        let fens = [
+            Board::KIWIPETE,
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+        for fen in fens {
+            let mut s = State::new();
+            let mut b = Board::new(fen, &mut s).unwrap();
+            let mut ev = Evaluator::new(&net, &b);
+            walk(&net, &mut ev, &mut b, &mut s, 2);
+        }
+
+        let mut s = State::new();
+        let mut b = Board::new(Board::KIWIPETE, &mut s).unwrap();
+        let mut ev = Evaluator::new(&net, &b);
+        b.do_null_move(&mut s).unwrap();
+        ev.push(&b, &s);
+        assert_eq!(ev.evaluate(&b), net.evaluate(&b));
+    }
+
+    #[test]
+    fn simd_matches_scalar() {
+        let net = random_network(64);
+        let column = net.column(1000);
+
+        let mut expected = net.ft_bias.clone();
+        let mut got = net.ft_bias.clone();
+        simd::scalar::add(&mut expected, column);
+        simd::add(&mut got, column);
+        assert_eq!(got, expected);
+
+        simd::scalar::sub(&mut expected, net.column(7));
+        simd::sub(&mut got, net.column(7));
+        assert_eq!(got, expected);
+
+        let expected = simd::scalar::output(&got, &net.out_weights[..64]);
+        assert_eq!(simd::output(&got, &net.out_weights[..64]), expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        INIT.call_once(initialize_bitboards);
+
+        let net = random_network(16);
+        let mut bytes = Vec::new();
+        net.write(&mut bytes).unwrap();
+        let read = Network::read(bytes.as_slice()).unwrap();
+
+        let mut s = State::new();
+        let b = Board::new(Board::STARTPOS, &mut s).unwrap();
+        assert_eq!(read.hidden_size(), 16);
+        assert_eq!(read.evaluate(&b), net.evaluate(&b));
+
+        bytes[4] = 15;
+        assert!(Network::read(bytes.as_slice()).is_err());
+    }
+}
@@ -46,6 +46,70 @@ fn perft__<const ROOT: bool>(board: &mut Board, state: &mut State, depth: usize)
     nodes
 }
 
+/// The positions [`bench`] counts, chosen to cover castling, en passant,
+/// promotions, pins and checks between them
+pub const BENCH_POSITIONS: [&str; 6] = [
+    Board::STARTPOS,
+    Board::KIWIPETE,
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+/// The depth [`bench`] is run at when none is given
+pub const BENCH_DEPTH: usize = 4;
+
+/// The total perft count of [`BENCH_POSITIONS`] to `depth`, with the root
+/// moves of each position shared out between `threads` threads.
+///
+/// The total depends only on the move generator, not on the number of
+/// threads or the machine, so it can be compared between commits.
+pub fn bench(depth: usize, threads: usize) -> usize {
+    assert!(depth > 0 && threads > 0);
+
+    let mut nodes = 0;
+    for fen in BENCH_POSITIONS {
+        let mut state = State::new();
+        let board = Board::new(fen, &mut state).unwrap();
+        let moves = movegen::generate_legal(&board, &state);
+        let moves = moves.as_slice();
+
+        nodes += std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|t| {
+                    let (mut board, mut state) = board.clone(&state);
+                    scope.spawn(move || {
+                        let mut nodes = 0;
+                        for &m in moves.iter().skip(t).step_by(threads) {
+                            board.do_move(&mut state, m).unwrap();
+                            nodes += count(&mut board, &mut state, depth - 1);
+                            board.undo_move(&mut state, m);
+                        }
+                        nodes
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .map(|w| w.join().unwrap())
+                .sum::<usize>()
+        });
+    }
+
+    nodes
+}
+
+/// [`perft__`] without the output, for any depth
+fn count(board: &mut Board, state: &mut State, depth: usize) -> usize {
+    match depth {
+        0 => 1,
+        1 => movegen::generate_legal(board, state).len(),
+        _ => perft__::<false>(board, state, depth),
+    }
+}
+
 macro_rules! setup_perft {
     ($expected:literal, $depth:literal) => {
         setup_perft!($crate::prelude::Board::STARTPOS, $expected, $depth);
@@ -59,7 +123,7 @@ macro_rules! setup_perft {
 
 #[cfg(test)]
 mod starting_position {
-    use crate::bitboard::initialize_bitboards as bb_init;
+    use crate::spine::bitboard::initialize_bitboards as bb_init;
     use std::sync::Once;
 
     static INIT: Once = Once::new();
@@ -162,7 +226,7 @@ mod kiwipete {
 #[cfg(test)]
 mod pos3 {
     use super::starting_position::init;
-    const FEN: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -";
+    pub(super) const FEN: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -";
 
     mod shallow {
         use super::init;
@@ -202,3 +266,156 @@ mod pos3 {
         }
     }
 }
+
+#[cfg(test)]
+mod transformed {
+    use super::starting_position::init;
+    use crate::prelude::*;
+
+    const POSITIONS: [&str; 3] = [Board::STARTPOS, Board::KIWIPETE, super::pos3::FEN];
+
+    fn perft_of((mut board, mut state): (Board, State), depth: usize) -> usize {
+        crate::perft::perft_on(&mut board, &mut state, depth)
+    }
+
+    #[test]
+    fn colour_flip() {
+        init();
+        for fen in POSITIONS {
+            let mut s = State::new();
+            let b = Board::new(fen, &mut s).unwrap();
+            let expected = perft_of(b.clone(&s), 3);
+
+            let flipped = b.mirror_vertical(&s);
+            assert_ne!(flipped.0.to_move(), b.to_move());
+            assert_eq!(perft_of(flipped, 3), expected, "{fen}");
+        }
+    }
+
+    #[test]
+    fn mirror_and_rotate() {
+        init();
+        let mut s = State::new();
+        let b = Board::new(super::pos3::FEN, &mut s).unwrap();
+        let expected = perft_of(b.clone(&s), 4);
+
+        assert_eq!(perft_of(b.mirror_horizontal(&s), 4), expected);
+        assert_eq!(perft_of(b.rotate_180(&s), 4), expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        init();
+        for fen in POSITIONS {
+            let mut s = State::new();
+            let b = Board::new(fen, &mut s).unwrap();
+            let (fb, fs) = b.mirror_vertical(&s);
+            let (rb, rs) = fb.mirror_vertical(&fs);
+
+            for i in 0..Square::COUNT as u8 {
+                let sq = Square::new(i);
+                assert_eq!(b.get_piece(sq), rb.get_piece(sq), "{fen}");
+            }
+            assert_eq!(b.to_move(), rb.to_move());
+            assert_eq!(s.castle_rights(), rs.castle_rights());
+            assert_eq!(s.en_passant(), rs.en_passant());
+        }
+    }
+}
+
+#[cfg(test)]
+mod retracted {
+    use super::starting_position::init;
+    use crate::movegen::{self, Unmove};
+    use crate::prelude::*;
+
+    const POSITIONS: [&str; 5] = [
+        Board::STARTPOS,
+        Board::KIWIPETE,
+        super::pos3::FEN,
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    ];
+
+    fn check_unmoves(board: &mut Board, state: &mut State, depth: usize) {
+        for &m in movegen::generate_legal(board, state).iter() {
+            let uncapture = match m.flag() {
+                MoveFlag::EnPassant => Some(PieceType::Pawn),
+                MoveFlag::Castle => None,
+                _ => board.get_piece(m.to_square()).map(|p| p.kind()),
+            };
+            let unmove = Unmove { mv: m, uncapture };
+            let (before, before_state) = board.clone(state);
+
+            board.do_move(state, m).unwrap();
+            let unmoves = movegen::generate_unmoves(board, state);
+            let found = unmoves.iter().filter(|&&u| u == unmove).count();
+            assert_eq!(found, 1, "{m} {uncapture:?} in\n{board}");
+
+            let (b, s) = board.retract(state, unmove).unwrap();
+            for i in 0..Square::COUNT as u8 {
+                let sq = Square::new(i);
+                assert_eq!(b.get_piece(sq), before.get_piece(sq), "{m}");
+            }
+            assert_eq!(b.to_move(), before.to_move(), "{m}");
+            assert_eq!(b.ply(), before.ply(), "{m}");
+
+            // What `retract` can't know is filled in as its doc says: rights
+            // the move took away are lost, the en passant square is only kept
+            // for an en passant capture, and the clock is 0 after a capture or
+            // pawn move. Everything else has to match.
+            let mut rights = state.castle_rights();
+            if m.flag() == MoveFlag::Castle {
+                let short = m.to_square().file() > m.from_square().file();
+                let [short_right, long_right] = rights.mut_rights_for(before.to_move());
+                *(if short { short_right } else { long_right }) =
+                    Some(CastleRight::standard(before.to_move(), short));
+            }
+            assert_eq!(s.castle_rights(), rights, "{m}");
+            if before_state.castle_rights() == state.castle_rights() {
+                assert_eq!(s.castle_rights(), before_state.castle_rights(), "{m}");
+            }
+
+            if m.flag() == MoveFlag::EnPassant || before_state.en_passant().is_none() {
+                assert_eq!(s.en_passant(), before_state.en_passant(), "{m}");
+            } else {
+                assert_eq!(s.en_passant(), None, "{m}");
+            }
+
+            if state.half_moves() == 0 {
+                assert_eq!(s.half_moves(), 0, "{m}");
+            } else {
+                assert_eq!(s.half_moves(), before_state.half_moves(), "{m}");
+            }
+            if depth > 1 {
+                check_unmoves(board, state, depth - 1);
+            }
+            board.undo_move(state, m);
+        }
+    }
+
+    #[test]
+    fn undo_every_move() {
+        init();
+        for fen in POSITIONS {
+            let mut s = State::new();
+            let mut b = Board::new(fen, &mut s).unwrap();
+            check_unmoves(&mut b, &mut s, 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod bench {
+    use super::starting_position::init;
+    use crate::perft::bench;
+
+    #[test]
+    fn signature() {
+        init();
+        // The sum of the published perft(3) counts of every position
+        assert_eq!(bench(3, 1), 271_312);
+        assert_eq!(bench(3, 3), 271_312);
+        assert_eq!(bench(1, 64), 20 + 48 + 14 + 6 + 44 + 46);
+    }
+}
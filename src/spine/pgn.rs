@@ -0,0 +1,445 @@
+use crate::prelude::*;
+
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// How a finished game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The given side won
+    Win(Color),
+    /// Nobody won
+    Draw,
+}
+
+impl Outcome {
+    /// Read a PGN game termination marker, `1-0`, `0-1` or `1/2-1/2`
+    pub fn from_pgn(s: &str) -> Option<Self> {
+        match s {
+            "1-0" => Some(Self::Win(Color::White)),
+            "0-1" => Some(Self::Win(Color::Black)),
+            "1/2-1/2" => Some(Self::Draw),
+            _ => None,
+        }
+    }
+
+    /// The PGN game termination marker for the outcome
+    pub const fn to_pgn(self) -> &'static str {
+        match self {
+            Self::Win(Color::White) => "1-0",
+            Self::Win(Color::Black) => "0-1",
+            Self::Draw => "1/2-1/2",
+        }
+    }
+}
+
+/// A single game read from a PGN file. Comments, variations and
+/// annotations are dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    /// The tag pairs, in the order they were given
+    pub tags: Vec<(String, String)>,
+    /// The moves of the main line, in SAN
+    pub moves: Vec<String>,
+    /// The result, or `None` if the game is unfinished
+    pub outcome: Option<Outcome>,
+}
+
+impl PgnGame {
+    /// The value of the tag called `name`, if there is one
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The position the game starts from, which is the standard one unless
+    /// there is a `FEN` tag
+    pub fn start(&self) -> Result<(Board, State), BoardCreationError> {
+        let mut state = State::new();
+        let board = Board::new(self.tag("FEN").unwrap_or(Board::STARTPOS), &mut state)?;
+        Ok((board, state))
+    }
+
+    /// Play through the game, calling `f` with the position before each move
+    /// and the move itself. When it returns `false` that move is the last
+    /// one: the rest of the game is not read, so it may hold illegal moves.
+    /// Returns the number of moves played.
+    pub fn replay<F>(&self, mut f: F) -> Result<usize, PgnError>
+    where
+        F: FnMut(&Board, &State, Move) -> bool,
+    {
+        let (mut board, mut state) = self.start().map_err(PgnError::InvalidFen)?;
+        let mut played = Vec::with_capacity(self.moves.len());
+
+        let mut res = Ok(());
+        for (ply, san) in self.moves.iter().enumerate() {
+            let Some(mv) = board.parse_san(&state, san) else {
+                res = Err(PgnError::IllegalMove {
+                    ply,
+                    san: san.clone(),
+                });
+                break;
+            };
+
+            let more = f(&board, &state, mv);
+            board.do_move(&mut state, mv).unwrap();
+            played.push(mv);
+            if !more {
+                break;
+            }
+        }
+
+        let n = played.len();
+        // Unwind so that the states made by `do_move` are freed
+        for mv in played.into_iter().rev() {
+            board.undo_move(&mut state, mv);
+        }
+
+        res.map(|_| n)
+    }
+}
+
+/// Reads [`PgnGame`]s one at a time from some text
+#[derive(Debug)]
+pub struct PgnReader<R> {
+    reader: R,
+    line: String,
+    pending: Option<String>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    /// Read games from `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            pending: None,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.line.clone()))
+    }
+
+    fn read_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        let mut game = PgnGame::default();
+        let mut movetext = String::new();
+        let mut in_comment = false;
+
+        while let Some(line) = self.next_line()? {
+            let trimmed = line.trim();
+            if trimmed.starts_with('%') {
+                continue;
+            }
+
+            if !in_comment && trimmed.starts_with('[') {
+                if !movetext.trim().is_empty() {
+                    // This is the start of the next game
+                    self.pending = Some(line);
+                    break;
+                }
+                game.tags.push(parse_tag(trimmed)?);
+                continue;
+            }
+
+            let mut code_end = line.len();
+            for (i, c) in line.char_indices() {
+                match c {
+                    '{' => in_comment = true,
+                    '}' => in_comment = false,
+                    ';' if !in_comment => {
+                        code_end = i;
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+            movetext.push_str(&line);
+            movetext.push('\n');
+
+            // A termination marker ends the game, even if no tags follow
+            let last = line[..code_end].split_whitespace().next_back();
+            if !in_comment && last.is_some_and(|t| t == "*" || Outcome::from_pgn(t).is_some()) {
+                break;
+            }
+        }
+
+        if game.tags.is_empty() && movetext.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let (moves, outcome) = parse_movetext(&movetext);
+        game.moves = moves;
+        game.outcome = match outcome {
+            Some(o) => o,
+            None => game.tag("Result").and_then(Outcome::from_pgn),
+        };
+
+        Ok(Some(game))
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_game().transpose()
+    }
+}
+
+fn parse_tag(line: &str) -> Result<(String, String), PgnError> {
+    let invalid = || PgnError::InvalidTag(line.to_string());
+
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+    let (name, value) = inner
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unescaped.extend(chars.next());
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    Ok((name.to_string(), unescaped))
+}
+
+/// Split movetext into the SAN moves of the main line, and the game
+/// termination marker if there is one (`Some(None)` for `*`).
+fn parse_movetext(text: &str) -> (Vec<String>, Option<Option<Outcome>>) {
+    let mut moves = Vec::new();
+    let mut outcome = None;
+    let mut depth = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() => (),
+            c => {
+                let mut token = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}();".contains(c) {
+                        break;
+                    }
+                    token.push(c);
+                    let _ = chars.next();
+                }
+
+                if depth > 0 || token.starts_with('$') {
+                    continue;
+                }
+                if token == "*" {
+                    outcome = Some(None);
+                    continue;
+                }
+                if let Some(o) = Outcome::from_pgn(&token) {
+                    outcome = Some(Some(o));
+                    continue;
+                }
+
+                // Move numbers, which may be stuck to the move: `12.`, `12...Nf6`
+                let san = match token.rfind('.') {
+                    Some(i) => &token[i + 1..],
+                    None => &token,
+                };
+                if !san.is_empty() {
+                    moves.push(san.to_string());
+                }
+            }
+        }
+    }
+
+    (moves, outcome)
+}
+
+/// The ways reading a PGN may fail
+#[derive(Debug)]
+pub enum PgnError {
+    /// The underlying reader failed
+    Io(io::Error),
+    /// A tag pair line is not of the form `[Name "Value"]`
+    InvalidTag(String),
+    /// The `FEN` tag could not be used
+    InvalidFen(BoardCreationError),
+    /// The move at `ply` (counting from zero) is not legal or not SAN
+    IllegalMove { ply: usize, san: String },
+}
+
+impl From<io::Error> for PgnError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::InvalidTag(line) => write!(f, "invalid tag pair `{line}`"),
+            Self::InvalidFen(e) => write!(f, "{e}"),
+            Self::IllegalMove { ply, san } => write!(f, "illegal move `{san}` at ply {ply}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::InvalidFen(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    const GAMES: &str = r#"[Event "Casual \"Game\""]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {A comment
+over two lines} Nc6 (2... d6 3. d4 (3. Bc4)) 3. Bb5 $1 a6?! ; rest of line
+4.Ba4 Nf6 5. 0-0 1-0
+
+[Event "Second"]
+[Result "*"]
+
+1. d4 d5 2. c4 *
+[Event "Third"]
+[SetUp "1"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 1"]
+[Result "1/2-1/2"]
+
+1. a8=Q+ Kd7 1/2-1/2
+"#;
+
+    fn games() -> Vec<PgnGame> {
+        PgnReader::new(GAMES.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn reading() {
+        let games = games();
+        assert_eq!(games.len(), 3);
+
+        assert_eq!(games[0].tag("Event"), Some("Casual \"Game\""));
+        assert_eq!(games[0].tag("White"), Some("A"));
+        assert_eq!(
+            games[0].moves,
+            ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6?!", "Ba4", "Nf6", "0-0"]
+        );
+        assert_eq!(games[0].outcome, Some(Outcome::Win(Color::White)));
+
+        assert_eq!(games[1].moves, ["d4", "d5", "c4"]);
+        assert_eq!(games[1].outcome, None);
+
+        assert_eq!(games[2].moves, ["a8=Q+", "Kd7"]);
+        assert_eq!(games[2].outcome, Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn replaying() {
+        INIT.call_once(initialize_bitboards);
+        let games = games();
+
+        let mut seen = Vec::new();
+        let n = games[0]
+            .replay(|b, _, m| {
+                seen.push((b.to_move(), m));
+                true
+            })
+            .unwrap();
+        assert_eq!(n, 9);
+        assert_eq!(seen[4].0, Color::White);
+        assert_eq!(seen[4].1.to_square(), Square::B5);
+        assert_eq!(seen[8].1.flag(), MoveFlag::Castle);
+
+        let n = games[2].replay(|_, _, _| true).unwrap();
+        assert_eq!(n, 2);
+
+        // Stopping early
+        let mut left = 3;
+        let n = games[0]
+            .replay(|_, _, _| {
+                left -= 1;
+                left > 0
+            })
+            .unwrap();
+        assert_eq!(n, 3);
+
+        let mut bad = games[1].clone();
+        bad.moves.push("Qxh7".to_string());
+        assert!(matches!(
+            bad.replay(|_, _, _| true),
+            Err(PgnError::IllegalMove { ply: 3, .. })
+        ));
+        // Moves after the last one asked for are never read
+        assert_eq!(bad.replay(|_, _, _| false).unwrap(), 1);
+    }
+
+    #[test]
+    fn without_tags() {
+        let games: Vec<_> = PgnReader::new("1. e4 e5 1-0\n1. d4 *\n\n1. c4\n".as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].moves, ["e4", "e5"]);
+        assert_eq!(games[1].moves, ["d4"]);
+        assert_eq!(games[2].moves, ["c4"]);
+        assert_eq!(games[2].outcome, None);
+    }
+
+    #[test]
+    fn bad_tag() {
+        let mut reader = PgnReader::new("[Event Casual]\n1. e4 *\n".as_bytes());
+        assert!(matches!(reader.next(), Some(Err(PgnError::InvalidTag(_)))));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::movegen::Unmove;
+use crate::prelude::*;
+
+use ShiftDir::*;
+
+impl Board {
+    /// Take back `unmove`, one of the moves from
+    /// [`generate_unmoves`](crate::movegen::generate_unmoves), returning the
+    /// position it was played from.
+    ///
+    /// Some of the position before can't be told from this one, so it is
+    /// filled in as follows:
+    ///
+    /// - Castling rights are the ones of this position, plus the one used if
+    ///   `unmove` is castling. Rights that a king or rook move, or the
+    ///   capture of a rook, took away are lost.
+    /// - There is only an en passant square when taking back an en passant
+    ///   capture. The position may have had one whatever the move was.
+    /// - The half-move clock is one less after taking back a move that
+    ///   doesn't reset it, castling included. After a capture or pawn move
+    ///   the old count is unknown, and 0 is used.
+    ///
+    /// # Panics
+    ///
+    /// If the side that just moved has no piece on the destination square.
+    pub fn retract(&self, state: &State, unmove: Unmove) -> Result<(Board, State), PositionError> {
+        let mv = unmove.mv;
+        let (from, to) = (mv.from_square(), mv.to_square());
+        let them = !self.to_move();
+        let piece = self
+            .get_piece(to)
+            .filter(|p| p.color() == them)
+            .expect("the unmove's piece should be on its destination square");
+
+        let mut builder = BoardBuilder::from_board(self, state);
+        builder.clear(to).side_to_move(them).en_passant(None);
+
+        match mv.flag() {
+            MoveFlag::Promotion => builder.put(from, PieceType::Pawn + them),
+            _ => builder.put(from, piece),
+        };
+
+        match (mv.flag(), unmove.uncapture) {
+            (MoveFlag::EnPassant, _) => {
+                builder
+                    .put(to + Backward(them), PieceType::Pawn + !them)
+                    .en_passant(Some(to));
+            }
+            (MoveFlag::Castle, _) => {
+                let short = to.file() > from.file();
+                let right = CastleRight::standard(them, short);
+                builder
+                    .clear(right.rook_to)
+                    .put(right.rook_from, PieceType::Rook + them)
+                    .castling(them, short);
+            }
+            (_, Some(kind)) => {
+                builder.put(to, kind + !them);
+            }
+            (_, None) => (),
+        }
+
+        let irreversible = piece.kind() == PieceType::Pawn
+            || unmove.uncapture.is_some()
+            || matches!(mv.flag(), MoveFlag::Promotion | MoveFlag::EnPassant);
+        let half_moves = if irreversible {
+            0
+        } else {
+            state.half_moves().saturating_sub(1)
+        };
+
+        builder
+            .half_moves(half_moves)
+            .full_moves(self.ply().saturating_sub(1))
+            .build_lenient()
+    }
+}
@@ -0,0 +1,828 @@
+//! Exact depth-to-mate tables for endgames of up to four pieces, made by
+//! retrograde analysis.
+//!
+//! A table holds every placement of its pieces up to symmetry with either
+//! side to move, one byte a position. Generation starts from the mates, and
+//! walks back through the moves that lead to each decided position until
+//! nothing changes. Captures and promotions lead out of the table; those
+//! positions are looked up in the smaller tables, which are generated first.
+//!
+//! Only placements where the kings don't touch are kept, and of those, only
+//! one of each set that mirror into each other. Without pawns, the board can
+//! be mirrored across files, ranks and the a1-h8 diagonal, so the white king
+//! is kept in the a1-d1-d4 triangle, and the black king on or below the
+//! diagonal when the white king is on it: 462 pairs of king squares. With
+//! pawns, only files can be mirrored, so the white king is kept on files a to
+//! d: 1806 pairs.
+//!
+//! A saved table is the bytes `CDTM`, the number of pieces, the material as
+//! in [`Board::material_signature`], then one byte for every position. The
+//! position of a byte is, from the most significant digit down: the pair of
+//! king squares, in the order of square numbers with the white king first;
+//! the square of each other piece in the order of the material, in base 64;
+//! and the side to move, white first. Byte values are described at
+//! [`Table::probe`]; impossible positions, such as two pieces on one square,
+//! are scored as draws.
+//!
+//! Castling and en passant are left out. Positions where either is possible
+//! aren't in the tables, and a double step is scored as if it couldn't be
+//! taken en passant.
+
+use crate::movegen;
+use crate::prelude::*;
+use crate::spine::material::SIGNATURE_ORDER;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use PieceType::*;
+
+/// The most pieces, kings included, a table can be made for
+pub const MAX_PIECES: usize = 4;
+
+const MAGIC: &[u8; 4] = b"CDTM";
+
+/// The distance to mate of every position with some material
+#[derive(Clone)]
+pub struct Table {
+    pieces: Vec<Piece>,
+    kings: KingPairs,
+    dtm: Vec<u8>,
+}
+
+impl Table {
+    /// Generate the table for the material in `signature`, such as `KQkr`.
+    ///
+    /// Returns `None` unless there is one king each and at most
+    /// [`MAX_PIECES`] pieces in all.
+    pub fn generate(signature: &str) -> Option<Self> {
+        let pieces = parse_signature(signature)?;
+        let mut tables = HashMap::new();
+        generate_into(&pieces, &mut tables);
+        tables.remove(&signature_of(&pieces))
+    }
+
+    /// Generate the table for `signature` along with every table its captures
+    /// and promotions lead to, smallest first
+    pub fn generate_all(signature: &str) -> Option<Vec<Self>> {
+        let pieces = parse_signature(signature)?;
+        let mut tables = HashMap::new();
+        generate_into(&pieces, &mut tables);
+
+        let mut tables: Vec<_> = tables.into_values().collect();
+        tables.sort_by_key(|t| (t.pieces.len(), t.signature()));
+        Some(tables)
+    }
+
+    /// The material of the table, as in [`Board::material_signature`]
+    pub fn signature(&self) -> String {
+        signature_of(&self.pieces)
+    }
+
+    /// The number of positions in the table, including impossible ones with
+    /// two pieces on a square or pawns on the back rank
+    pub fn len(&self) -> usize {
+        self.dtm.len()
+    }
+
+    /// Whether the table has no positions, which is never the case
+    pub fn is_empty(&self) -> bool {
+        self.dtm.is_empty()
+    }
+
+    /// The score of the position, from the point of view of the side to move:
+    /// a mate with its exact distance, or a draw.
+    ///
+    /// Returns `None` if the material doesn't match, or if castling or en
+    /// passant is possible.
+    ///
+    /// In the table, a draw is 0, and a mate is one more than the number of
+    /// plies to it. An odd number of plies means the side to move mates.
+    pub fn probe(&self, board: &Board, state: &State) -> Option<Score> {
+        if board.material_signature() != self.signature()
+            || state.en_passant().is_some()
+            || !state.castle_rights().all_none()
+        {
+            return None;
+        }
+
+        Some(self.score(board))
+    }
+
+    fn new(pieces: Vec<Piece>) -> Self {
+        let pawns = pieces.iter().any(|p| p.kind() == Pawn);
+        let kings = KingPairs::new(pawns);
+        let size = kings.pairs.len() << (6 * (pieces.len() - 2) + 1);
+        Self {
+            pieces,
+            kings,
+            dtm: vec![0; size],
+        }
+    }
+
+    /// The score of `board`, whose material must be that of the table
+    fn score(&self, board: &Board) -> Score {
+        let squares = squares_of(&self.pieces, board);
+        let slot = self
+            .slot(board.to_move(), &squares[..self.pieces.len()])
+            .expect("kings of a legal position don't touch");
+        decode(self.dtm[slot])
+    }
+
+    fn black_king(&self) -> usize {
+        self.pieces
+            .iter()
+            .position(|&p| p == King + Color::Black)
+            .unwrap()
+    }
+
+    /// Where the position is kept in the table, after mirroring it so that
+    /// the kings are on one of the kept pairs. `None` if the kings touch.
+    fn slot(&self, to_move: Color, squares: &[Square]) -> Option<usize> {
+        let bk = self.black_king();
+        let symmetries = if self.kings.pawns { 2 } else { 8 };
+        let (symmetry, pair) = (0..symmetries).find_map(|t| {
+            let kings = (mirror(squares[0], t), mirror(squares[bk], t));
+            self.kings.find(kings).map(|pair| (t, pair))
+        })?;
+
+        let placement = squares
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 0 && i != bk)
+            .fold(pair, |i, (_, &s)| (i << 6) | mirror(s, symmetry).to_usize());
+        Some((placement << 1) | to_move.to_usize())
+    }
+
+    /// The position kept at `slot`
+    fn unslot(&self, slot: usize) -> (Color, [Square; MAX_PIECES]) {
+        let to_move = if slot & 1 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+
+        let bk = self.black_king();
+        let mut squares = [Square::A1; MAX_PIECES];
+        let mut placement = slot >> 1;
+        for i in (1..self.pieces.len()).rev().filter(|&i| i != bk) {
+            squares[i] = Square::new((placement & 63) as u8);
+            placement >>= 6;
+        }
+        (squares[0], squares[bk]) = self.kings.pairs[placement];
+
+        (to_move, squares)
+    }
+
+    /// The longest win in the table, which is the hardest position to mate from
+    pub fn longest_win(&self) -> Option<Score> {
+        self.dtm
+            .iter()
+            .map(|&d| decode(d))
+            .filter(|&s| s > Score::DRAW)
+            .min()
+    }
+
+    /// Write the table out
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[self.pieces.len() as u8])?;
+        writer.write_all(self.signature().as_bytes())?;
+        writer.write_all(&self.dtm)?;
+        writer.flush()
+    }
+
+    /// Read a table written by [`Table::write`]
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] as usize > MAX_PIECES {
+            return Err(invalid("not a table"));
+        }
+
+        let mut signature = vec![0; header[4] as usize];
+        reader.read_exact(&mut signature)?;
+        let pieces = std::str::from_utf8(&signature)
+            .ok()
+            .and_then(parse_signature)
+            .ok_or_else(|| invalid("invalid material"))?;
+
+        let mut table = Self::new(pieces);
+        reader.read_exact(&mut table.dtm)?;
+
+        Ok(table)
+    }
+
+    /// Save the table to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Load a table saved by [`Table::save`]
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Table")
+            .field("signature", &self.signature())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// The pairs of king squares a table keeps, and where each is kept
+#[derive(Clone)]
+struct KingPairs {
+    pawns: bool,
+    pairs: Vec<(Square, Square)>,
+    /// The place of each pair in `pairs`, by the white king's square times
+    /// 64 plus the black king's, or `u16::MAX` if it isn't kept
+    slots: Vec<u16>,
+}
+
+impl KingPairs {
+    fn new(pawns: bool) -> Self {
+        let mut pairs = Vec::new();
+        let mut slots = vec![u16::MAX; 64 * 64];
+        for (wk, bk) in (0..64usize).flat_map(|w| (0..64).map(move |b| (w, b))) {
+            let (wf, wr, bf, br) = (wk & 7, wk >> 3, bk & 7, bk >> 3);
+            let apart = wf.abs_diff(bf) > 1 || wr.abs_diff(br) > 1;
+            let kept = if pawns {
+                wf < 4
+            } else {
+                wf < 4 && wr <= wf && (wr < wf || br <= bf)
+            };
+
+            if apart && kept {
+                slots[wk * 64 + bk] = pairs.len() as u16;
+                pairs.push((Square::new(wk as u8), Square::new(bk as u8)));
+            }
+        }
+
+        Self {
+            pawns,
+            pairs,
+            slots,
+        }
+    }
+
+    fn find(&self, (wk, bk): (Square, Square)) -> Option<usize> {
+        match self.slots[wk.to_usize() * 64 + bk.to_usize()] {
+            u16::MAX => None,
+            pair => Some(pair as usize),
+        }
+    }
+}
+
+/// One of the eight symmetries of the board, which mirrors files if bit 0 of
+/// `symmetry` is set, ranks if bit 1 is, and then the a1-h8 diagonal if bit
+/// 2 is
+fn mirror(square: Square, symmetry: usize) -> Square {
+    let mut s = square.to_u8();
+    if symmetry & 1 != 0 {
+        s ^= 7;
+    }
+    if symmetry & 2 != 0 {
+        s ^= 56;
+    }
+    if symmetry & 4 != 0 {
+        s = (s >> 3) | ((s & 7) << 3);
+    }
+    Square::new(s)
+}
+
+/// The size of the space [`Generator`] works in, which has every placement
+fn table_size(pieces: usize) -> usize {
+    2 << (6 * pieces)
+}
+
+fn index(to_move: Color, squares: &[Square]) -> usize {
+    let placement = squares.iter().rev().fold(0, |i, s| (i << 6) | s.to_usize());
+    (placement << 1) | to_move.to_usize()
+}
+
+/// The squares of the pieces of `board`, in the order of `pieces`, which
+/// must be its material
+fn squares_of(pieces: &[Piece], board: &Board) -> [Square; MAX_PIECES] {
+    let mut squares = [Square::A1; MAX_PIECES];
+    let mut i = 0;
+    while i < pieces.len() {
+        let p = pieces[i];
+        for s in board.spec(p.color(), p.kind()) {
+            squares[i] = s;
+            i += 1;
+        }
+    }
+    squares
+}
+
+/// The index of `board`, whose material must be `pieces`
+fn index_of(pieces: &[Piece], board: &Board) -> usize {
+    let squares = squares_of(pieces, board);
+    index(board.to_move(), &squares[..pieces.len()])
+}
+
+fn unindex(idx: usize, n: usize) -> (Color, [Square; MAX_PIECES]) {
+    let to_move = if idx & 1 == 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    let mut squares = [Square::A1; MAX_PIECES];
+    for (i, s) in squares.iter_mut().take(n).enumerate() {
+        *s = Square::new(((idx >> (1 + 6 * i)) & 63) as u8);
+    }
+
+    (to_move, squares)
+}
+
+/// A byte of a table, as described at [`Table::probe`]
+fn decode(dtm: u8) -> Score {
+    match dtm {
+        0 => Score::DRAW,
+        d if d % 2 == 0 => Score::mate_in(d as usize - 1),
+        d => Score::mated_in(d as usize - 1),
+    }
+}
+
+fn encode(score: Score) -> u8 {
+    match score.mate_plies() {
+        Some(p) => u8::try_from(p.abs() + 1)
+            .expect("mates with four pieces are short enough to fit in a byte"),
+        None => 0,
+    }
+}
+
+/// The score of a position one ply before one scored `score`
+fn back(score: Score) -> Score {
+    match score.mate_plies() {
+        Some(p) if p > 0 => Score::mated_in(p as usize + 1),
+        Some(p) => Score::mate_in((-p) as usize + 1),
+        None => -score,
+    }
+}
+
+fn parse_signature(signature: &str) -> Option<Vec<Piece>> {
+    let mut pieces = Vec::with_capacity(MAX_PIECES);
+    for c in signature.chars() {
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let pt = match c.to_ascii_lowercase() {
+            'p' => Pawn,
+            'n' => Knight,
+            'b' => Bishop,
+            'r' => Rook,
+            'q' => Queen,
+            'k' => King,
+            _ => return None,
+        };
+        pieces.push(pt + color);
+    }
+
+    let kings = |c| pieces.iter().filter(|&&p| p == King + c).count();
+    if pieces.len() > MAX_PIECES || kings(Color::White) != 1 || kings(Color::Black) != 1 {
+        return None;
+    }
+
+    sort_pieces(&mut pieces);
+    Some(pieces)
+}
+
+fn sort_pieces(pieces: &mut [Piece]) {
+    let order = |p: &Piece| {
+        let kind = SIGNATURE_ORDER.iter().position(|&k| k == p.kind());
+        (p.color(), kind)
+    };
+    pieces.sort_by_key(order);
+}
+
+fn signature_of(pieces: &[Piece]) -> String {
+    pieces.iter().map(|p| p.to_string()).collect()
+}
+
+/// The material a capture or promotion can turn `pieces` into
+fn children(pieces: &[Piece]) -> Vec<Vec<Piece>> {
+    let without = |pieces: &[Piece], i: usize| {
+        let mut v = pieces.to_vec();
+        let _ = v.remove(i);
+        v
+    };
+
+    let mut out = Vec::new();
+    for (i, p) in pieces.iter().enumerate() {
+        if p.kind() != King {
+            out.push(without(pieces, i));
+        }
+
+        if p.kind() != Pawn {
+            continue;
+        }
+        for pt in [Knight, Bishop, Rook, Queen] {
+            let mut promoted = pieces.to_vec();
+            promoted[i] = pt + p.color();
+
+            for (j, q) in promoted.iter().enumerate() {
+                if q.color() != p.color() && q.kind() != King {
+                    out.push(without(&promoted, j));
+                }
+            }
+            out.push(promoted);
+        }
+    }
+
+    for c in out.iter_mut() {
+        sort_pieces(c);
+    }
+    out
+}
+
+fn generate_into(pieces: &[Piece], tables: &mut HashMap<String, Table>) {
+    if tables.contains_key(&signature_of(pieces)) {
+        return;
+    }
+    for child in children(pieces) {
+        generate_into(&child, tables);
+    }
+
+    let table = Generator::new(pieces, tables).run();
+    let _ = tables.insert(table.signature(), table);
+}
+
+const INVALID: u8 = 0;
+const OPEN: u8 = 1;
+const FINAL: u8 = 2;
+const DONE: u8 = 3;
+
+struct Generator<'a> {
+    pieces: &'a [Piece],
+    subtables: &'a HashMap<String, Table>,
+
+    /// The best score found so far for the side to move, as in [`Score`]
+    value: Vec<i16>,
+    /// The number of moves staying in the table not known to lose yet
+    remaining: Vec<u8>,
+    status: Vec<u8>,
+    /// Positions to look at, by their distance to mate
+    buckets: Vec<Vec<u32>>,
+    /// Double steps that give an en passant capture, which unmove generation
+    /// won't find, by the position they lead to
+    double_steps: HashMap<u32, Vec<u32>>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(pieces: &'a [Piece], subtables: &'a HashMap<String, Table>) -> Self {
+        let size = table_size(pieces.len());
+        Self {
+            pieces,
+            subtables,
+            value: vec![i16::MIN; size],
+            remaining: vec![0; size],
+            status: vec![INVALID; size],
+            buckets: Vec::new(),
+            double_steps: HashMap::new(),
+        }
+    }
+
+    fn position(&self, idx: usize) -> Option<(Board, State)> {
+        let (to_move, squares) = unindex(idx, self.pieces.len());
+
+        let mut builder = BoardBuilder::new();
+        for (&p, &s) in self.pieces.iter().zip(squares.iter()) {
+            let back_rank = matches!(s.rank(), Rank::One | Rank::Eight);
+            if builder.get(s).is_some() || (p.kind() == Pawn && back_rank) {
+                return None;
+            }
+            let _ = builder.put(s, p);
+        }
+
+        builder.side_to_move(to_move).build_lenient().ok()
+    }
+
+    fn index_of(&self, board: &Board) -> usize {
+        index_of(self.pieces, board)
+    }
+
+    fn push(&mut self, plies: usize, idx: usize) {
+        if self.buckets.len() <= plies {
+            self.buckets.resize(plies + 1, Vec::new());
+        }
+        self.buckets[plies].push(idx as u32);
+    }
+
+    fn set_final(&mut self, idx: usize, score: Score) {
+        self.value[idx] = score.value() as i16;
+        self.status[idx] = FINAL;
+        if let Some(p) = score.mate_plies() {
+            self.push(p.unsigned_abs() as usize, idx);
+        }
+    }
+
+    /// Score what can be scored from the position alone: mates, stalemates,
+    /// and the moves that leave the table
+    fn initialise(&mut self, idx: usize) {
+        let Some((mut board, mut state)) = self.position(idx) else {
+            return;
+        };
+        self.status[idx] = OPEN;
+
+        let moves = movegen::generate_legal(&board, &state);
+        if moves.len() == 0 {
+            let score = if state.checkers().gtz() {
+                Score::mated_in(0)
+            } else {
+                Score::DRAW
+            };
+            self.set_final(idx, score);
+            return;
+        }
+
+        let mut best = None;
+        let mut remaining = 0;
+        for &m in moves.iter() {
+            let mover = board.get_piece(m.from_square()).unwrap().kind();
+            let capture = board.get_piece(m.to_square()).is_some();
+            let double_step = mover == Pawn && m.from_square().distance(m.to_square()) == 2;
+
+            if capture || m.flag() == MoveFlag::Promotion {
+                board.do_move(&mut state, m).unwrap();
+                let score = self.subtables[&board.material_signature()].score(&board);
+                board.undo_move(&mut state, m);
+
+                best = best.max(Some(back(score)));
+            } else {
+                remaining += 1;
+                if double_step {
+                    board.do_move(&mut state, m).unwrap();
+                    if state.en_passant().is_some() {
+                        let child = self.index_of(&board) as u32;
+                        self.double_steps.entry(child).or_default().push(idx as u32);
+                    }
+                    board.undo_move(&mut state, m);
+                }
+            }
+        }
+
+        self.remaining[idx] = remaining;
+        match best {
+            Some(score) if remaining == 0 => self.set_final(idx, score),
+            Some(score) => {
+                self.value[idx] = score.value() as i16;
+                if score > Score::DRAW {
+                    // A win, but maybe not the quickest
+                    self.push(score.mate_plies().unwrap() as usize, idx);
+                }
+            }
+            None => (),
+        }
+    }
+
+    fn predecessors(&self, idx: usize) -> Vec<usize> {
+        let (board, state) = self.position(idx).unwrap();
+        let (to_move, squares) = unindex(idx, self.pieces.len());
+        let n = self.pieces.len();
+
+        let mut preds: Vec<usize> = movegen::generate_quiet_unmoves(&board, &state)
+            .into_iter()
+            .map(|u| {
+                let mut squares = squares;
+                let slot = squares[..n]
+                    .iter()
+                    .position(|&s| s == u.mv.to_square())
+                    .unwrap();
+                squares[slot] = u.mv.from_square();
+                index(!to_move, &squares[..n])
+            })
+            .collect();
+
+        if let Some(extra) = self.double_steps.get(&(idx as u32)) {
+            preds.extend(extra.iter().map(|&q| q as usize));
+        }
+        preds
+    }
+
+    /// Pass a decided position's score back to the positions leading to it
+    fn propagate(&mut self, idx: usize, plies: usize) {
+        let score = back(Score::new(self.value[idx] as i32));
+
+        for q in self.predecessors(idx) {
+            if self.status[q] != OPEN {
+                continue;
+            }
+
+            let best = Score::new(self.value[q] as i32).max(score);
+            if score > Score::DRAW {
+                if best == score && self.value[q] != score.value() as i16 {
+                    self.value[q] = score.value() as i16;
+                    self.push(plies + 1, q);
+                }
+                continue;
+            }
+
+            self.value[q] = best.value() as i16;
+            self.remaining[q] -= 1;
+            if self.remaining[q] == 0 && best <= Score::DRAW {
+                self.set_final(q, best);
+            }
+        }
+    }
+
+    fn run(mut self) -> Table {
+        let size = table_size(self.pieces.len());
+        for idx in 0..size {
+            self.initialise(idx);
+        }
+
+        let mut plies = 0;
+        while plies < self.buckets.len() {
+            for idx in std::mem::take(&mut self.buckets[plies]) {
+                let idx = idx as usize;
+                let score = Score::new(self.value[idx] as i32);
+                let distance = score.mate_plies().map(|p| p.unsigned_abs() as usize);
+
+                // Stale entries for positions that were decided another way
+                if distance != Some(plies) || self.status[idx] == DONE {
+                    continue;
+                }
+                if self.status[idx] == OPEN && score < Score::DRAW {
+                    continue;
+                }
+
+                self.status[idx] = DONE;
+                self.propagate(idx, plies);
+            }
+            plies += 1;
+        }
+
+        let mut table = Table::new(self.pieces.to_vec());
+        let n = self.pieces.len();
+        for slot in 0..table.len() {
+            let (to_move, squares) = table.unslot(slot);
+            let idx = index(to_move, &squares[..n]);
+            if self.status[idx] == DONE {
+                table.dtm[slot] = encode(Score::new(self.value[idx] as i32));
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endgame;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::io::Cursor;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    fn position(fen: &str) -> (Board, State) {
+        INIT.call_once(initialize_bitboards);
+        let mut s = State::new();
+        let b = Board::new(fen, &mut s).unwrap();
+        (b, s)
+    }
+
+    #[test]
+    fn signatures() {
+        assert!(Table::generate("KQ").is_none());
+        assert!(Table::generate("KQkrn").is_none());
+        assert!(Table::generate("KKk").is_none());
+
+        let mut pieces = parse_signature("kPKq").unwrap();
+        assert_eq!(signature_of(&pieces), "KPkq");
+        sort_pieces(&mut pieces);
+        assert_eq!(signature_of(&children(&pieces)[0]), "Kkq");
+    }
+
+    #[test]
+    fn king_pairs() {
+        INIT.call_once(initialize_bitboards);
+        assert_eq!(KingPairs::new(false).pairs.len(), 462);
+        assert_eq!(KingPairs::new(true).pairs.len(), 1806);
+
+        let table = Table::new(parse_signature("KRkn").unwrap());
+        assert_eq!(table.len(), 2 * 462 * 64 * 64);
+        for slot in (0..table.len()).step_by(997) {
+            let (to_move, squares) = table.unslot(slot);
+            assert_eq!(table.slot(to_move, &squares[..4]), Some(slot));
+        }
+    }
+
+    #[test]
+    fn kqk() {
+        INIT.call_once(initialize_bitboards);
+        let table = Table::generate("KQk").unwrap();
+
+        // Ten moves is the longest it takes
+        assert_eq!(table.longest_win(), Some(Score::mate_in(19)));
+
+        let (b, s) = position("k7/8/1K6/8/8/8/7Q/8 w - - 0 1");
+        assert_eq!(table.probe(&b, &s), Some(Score::mate_in(1)));
+        let (b, s) = position("k6Q/8/1K6/8/8/8/8/8 b - - 0 1");
+        assert_eq!(table.probe(&b, &s), Some(Score::mated_in(0)));
+        let (b, s) = position("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        assert_eq!(table.probe(&b, &s), Some(Score::DRAW));
+        // Black takes the queen
+        let (b, s) = position("8/8/8/8/8/2k5/3Q4/7K b - - 0 1");
+        assert_eq!(table.probe(&b, &s), Some(Score::DRAW));
+
+        let (b, s) = position(Board::STARTPOS);
+        assert_eq!(table.probe(&b, &s), None);
+
+        // Mirrored positions share an entry
+        for fen in [
+            "7k/8/6K1/8/8/8/Q7/8 w - - 0 1",
+            "8/7Q/8/8/8/1K6/8/k7 w - - 0 1",
+            "1Q6/8/8/8/8/8/5K2/7k w - - 0 1",
+        ] {
+            let (b, s) = position(fen);
+            assert_eq!(table.probe(&b, &s), Some(Score::mate_in(1)), "{fen}");
+        }
+
+        let mut bytes = Vec::new();
+        table.write(&mut bytes).unwrap();
+        let read = Table::read(Cursor::new(bytes)).unwrap();
+        assert_eq!(read.signature(), "KQk");
+        assert_eq!(read.dtm, table.dtm);
+    }
+
+    #[test]
+    fn kpk() {
+        INIT.call_once(initialize_bitboards);
+        let table = Table::generate("KPk").unwrap();
+
+        // Mate by promoting, on either side of the board
+        for fen in [
+            "k7/3P4/1K6/8/8/8/8/8 w - - 0 1",
+            "7k/4P3/6K1/8/8/8/8/8 w - - 0 1",
+        ] {
+            let (b, s) = position(fen);
+            assert_eq!(table.probe(&b, &s), Some(Score::mate_in(1)), "{fen}");
+        }
+
+        // Every position agrees with the KPK bitbase on who wins
+        for pawn in 8..56 {
+            for wk in 0..64 {
+                for bk in 0..64 {
+                    for to_move in [Color::White, Color::Black] {
+                        let squares = [wk, bk, pawn].map(Square::new);
+                        if wk == bk || pawn == wk || pawn == bk {
+                            continue;
+                        }
+                        let mut builder = BoardBuilder::new();
+                        builder
+                            .put(squares[0], King + Color::White)
+                            .put(squares[1], King + Color::Black)
+                            .put(squares[2], Pawn + Color::White)
+                            .side_to_move(to_move);
+                        let Ok((b, s)) = builder.build() else {
+                            continue;
+                        };
+                        if movegen::generate_legal(&b, &s).len() == 0 {
+                            continue;
+                        }
+
+                        let dtm = table.probe(&b, &s).unwrap();
+                        let bitbase = endgame::probe(&b, &s).unwrap();
+                        assert_eq!(dtm.cmp(&Score::DRAW), bitbase.cmp(&Score::DRAW), "\n{b}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn pawn_endings() {
+        INIT.call_once(initialize_bitboards);
+        let tables = Table::generate_all("KPk").unwrap();
+        let signatures: Vec<_> = tables.iter().map(|t| t.signature()).collect();
+        assert_eq!(signatures, ["Kk", "KBk", "KNk", "KPk", "KQk", "KRk"]);
+
+        let kpk = &tables[3];
+        let (b, s) = position("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1");
+        assert!(kpk.probe(&b, &s).unwrap() > Score::DRAW);
+        let (b, s) = position("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1");
+        assert_eq!(kpk.probe(&b, &s), Some(Score::DRAW));
+
+        // Mate by promoting
+        let (b, s) = position("k7/3P4/1K6/8/8/8/8/8 w - - 0 1");
+        assert_eq!(kpk.probe(&b, &s), Some(Score::mate_in(1)));
+
+        // The rook mates take longest
+        assert_eq!(tables[5].longest_win(), Some(Score::mate_in(31)));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::movegen;
+use crate::prelude::*;
+
+use PieceType::*;
+
+impl Board {
+    /// Find the legal [`Move`] written in
+    /// [SAN](https://en.wikipedia.org/wiki/Algebraic_notation_(chess)), such as
+    /// `Nbd7`, `exd6`, `e8=Q+` or `O-O`.
+    ///
+    /// Check and annotation marks are ignored. Returns `None` if there is no
+    /// such move, or if the text is ambiguous.
+    pub fn parse_san(&self, state: &State, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        let moves = movegen::generate_legal(self, state);
+
+        if let Some(short) = match san {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        } {
+            return moves.iter().copied().find(|m| {
+                m.flag() == MoveFlag::Castle && (m.to_square() > m.from_square()) == short
+            });
+        }
+
+        let b = san.as_bytes();
+        let (rest, promo) = match b {
+            [rest @ .., b'=', p] | [rest @ .., p @ (b'N' | b'B' | b'R' | b'Q')] => {
+                (rest, Some(piece_from_letter(*p)?))
+            }
+            _ => (b, None),
+        };
+
+        let [rest @ .., f @ b'a'..=b'h', r @ b'1'..=b'8'] = rest else {
+            return None;
+        };
+        let to = Square::new((f - b'a') + 8 * (r - b'1'));
+
+        let (pt, rest) = match rest {
+            [p @ (b'N' | b'B' | b'R' | b'Q' | b'K'), rest @ ..] => (piece_from_letter(*p)?, rest),
+            _ => (Pawn, rest),
+        };
+        let rest = rest.strip_suffix(b"x").unwrap_or(rest);
+
+        let (from_file, from_rank) = match rest {
+            [] => (None, None),
+            [f @ b'a'..=b'h'] => (Some(f - b'a'), None),
+            [r @ b'1'..=b'8'] => (None, Some(r - b'1')),
+            [f @ b'a'..=b'h', r @ b'1'..=b'8'] => (Some(f - b'a'), Some(r - b'1')),
+            _ => return None,
+        };
+
+        let mut found = None;
+        for &m in moves.iter() {
+            let from = m.from_square();
+            let ok = m.to_square() == to
+                && m.flag() != MoveFlag::Castle
+                && self.get_piece(from).map(|p| p.kind()) == Some(pt)
+                && from_file.is_none_or(|f| from.file().to_usize() == f as usize)
+                && from_rank.is_none_or(|r| from.rank().to_usize() == r as usize)
+                && match promo {
+                    Some(p) => m.flag() == MoveFlag::Promotion && m.promotion_type() == p,
+                    None => m.flag() != MoveFlag::Promotion,
+                };
+
+            if ok {
+                if found.is_some() {
+                    return None;
+                }
+                found = Some(m);
+            }
+        }
+
+        found
+    }
+
+    /// Write the legal `mv` in SAN, with as little disambiguation as needed
+    /// and a `+` or `#` mark for check or mate.
+    pub fn to_san(&self, state: &State, mv: Move) -> String {
+        let (from, to) = (mv.from_square(), mv.to_square());
+        let kind = self.get_piece(from).map_or(Pawn, |p| p.kind());
+        let capture = mv.flag() == MoveFlag::EnPassant || self.get_piece(to).is_some();
+
+        let mut san = String::new();
+        if mv.flag() == MoveFlag::Castle {
+            san += if to > from { "O-O" } else { "O-O-O" };
+        } else if kind == Pawn {
+            if capture {
+                san += &format!("{}x", from.to_string().remove(0));
+            }
+            san += &to.to_string();
+            if mv.flag() == MoveFlag::Promotion {
+                let promotion = (mv.promotion_type() + Color::White).to_string();
+                san += &format!("={promotion}");
+            }
+        } else {
+            san += &(kind + Color::White).to_string();
+
+            let others: Vec<Square> = movegen::generate_legal(self, state)
+                .iter()
+                .filter(|m| m.to_square() == to && m.from_square() != from)
+                .map(|m| m.from_square())
+                .filter(|&f| self.get_piece(f) == self.get_piece(from))
+                .collect();
+            if !others.is_empty() {
+                let from_text = from.to_string();
+                if others.iter().all(|o| o.file() != from.file()) {
+                    san.push_str(&from_text[..1]);
+                } else if others.iter().all(|o| o.rank() != from.rank()) {
+                    san.push_str(&from_text[1..]);
+                } else {
+                    san.push_str(&from_text);
+                }
+            }
+
+            if capture {
+                san.push('x');
+            }
+            san += &to.to_string();
+        }
+
+        let (mut board, mut state) = self.clone(state);
+        board.do_move(&mut state, mv).unwrap();
+        if state.checkers().gtz() {
+            let mate = movegen::generate_legal(&board, &state).len() == 0;
+            san.push(if mate { '#' } else { '+' });
+        }
+        board.undo_move(&mut state, mv);
+
+        san
+    }
+}
+
+fn piece_from_letter(c: u8) -> Option<PieceType> {
+    match c {
+        b'N' => Some(Knight),
+        b'B' => Some(Bishop),
+        b'R' => Some(Rook),
+        b'Q' => Some(Queen),
+        b'K' => Some(King),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::move_new;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    fn position(fen: &str) -> (Board, State) {
+        INIT.call_once(initialize_bitboards);
+        let mut s = State::new();
+        let b = Board::new(fen, &mut s).unwrap();
+        (b, s)
+    }
+
+    #[test]
+    fn simple() {
+        let (b, s) = position(Board::STARTPOS);
+        assert_eq!(b.parse_san(&s, "e4"), Some(move_new!("e2e4")));
+        assert_eq!(b.parse_san(&s, "Nf3"), Some(move_new!("g1f3")));
+        assert_eq!(b.parse_san(&s, "Nf3!?"), Some(move_new!("g1f3")));
+        assert_eq!(b.parse_san(&s, "Ng1f3"), Some(move_new!("g1f3")));
+        assert_eq!(b.parse_san(&s, "e5"), None);
+        assert_eq!(b.parse_san(&s, "Ke2"), None);
+        assert_eq!(b.parse_san(&s, "O-O"), None);
+        assert_eq!(b.parse_san(&s, "xyz"), None);
+    }
+
+    #[test]
+    fn kiwipete() {
+        let (b, s) = position(Board::KIWIPETE);
+        assert_eq!(b.parse_san(&s, "O-O"), Some(move_new!("O-O"; b, s)));
+        assert_eq!(b.parse_san(&s, "0-0-0"), Some(move_new!("O-O-O"; b, s)));
+        assert_eq!(b.parse_san(&s, "Bxa6"), Some(move_new!("e2a6")));
+        assert_eq!(b.parse_san(&s, "Nxf7"), Some(move_new!("e5f7")));
+        assert_eq!(b.parse_san(&s, "gxh3"), Some(move_new!("g2h3")));
+        assert_eq!(b.parse_san(&s, "dxe6"), Some(move_new!("d5e6")));
+        assert_eq!(b.parse_san(&s, "Ncb5"), Some(move_new!("c3b5")));
+    }
+
+    #[test]
+    fn ambiguity() {
+        let (b, s) = position("8/8/3k4/8/R7/8/4K3/R6R w - - 0 1");
+        assert_eq!(b.parse_san(&s, "Rd1"), None);
+        assert_eq!(b.parse_san(&s, "Rad1"), Some(move_new!("a1d1")));
+        assert_eq!(b.parse_san(&s, "Rhd1"), Some(move_new!("h1d1")));
+        assert_eq!(b.parse_san(&s, "Ra3"), None);
+        assert_eq!(b.parse_san(&s, "R1a3"), Some(move_new!("a1a3")));
+        assert_eq!(b.parse_san(&s, "R4a3"), Some(move_new!("a4a3")));
+        assert_eq!(b.parse_san(&s, "Ra1a3"), Some(move_new!("a1a3")));
+    }
+
+    #[test]
+    fn promotions() {
+        let (b, s) = position("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        let q = Move::new(Square::A7, Square::B8, MoveFlag::Promotion, Queen);
+        let n = Move::new(Square::A7, Square::B8, MoveFlag::Promotion, Knight);
+
+        assert_eq!(b.parse_san(&s, "axb8=Q+"), Some(q));
+        assert_eq!(b.parse_san(&s, "axb8Q"), Some(q));
+        assert_eq!(b.parse_san(&s, "axb8=N"), Some(n));
+        assert_eq!(b.parse_san(&s, "axb8"), None);
+        assert_eq!(b.parse_san(&s, "a8=Q"), Some(move_new!("a7a8q")));
+    }
+
+    #[test]
+    fn writing() {
+        let (b, s) = position(Board::KIWIPETE);
+        assert_eq!(b.to_san(&s, move_new!("O-O"; b, s)), "O-O");
+        assert_eq!(b.to_san(&s, move_new!("O-O-O"; b, s)), "O-O-O");
+        assert_eq!(b.to_san(&s, move_new!("e2a6")), "Bxa6");
+        assert_eq!(b.to_san(&s, move_new!("e5f7")), "Nxf7");
+        assert_eq!(b.to_san(&s, move_new!("g2h3")), "gxh3");
+        assert_eq!(b.to_san(&s, move_new!("c3b5")), "Nb5");
+        assert_eq!(b.to_san(&s, move_new!("a2a4")), "a4");
+
+        let (b, s) = position("8/8/3k4/8/R7/8/4K3/R6R w - - 0 1");
+        assert_eq!(b.to_san(&s, move_new!("a1d1")), "Rad1+");
+        assert_eq!(b.to_san(&s, move_new!("a1a3")), "R1a3");
+        assert_eq!(b.to_san(&s, move_new!("a4a3")), "R4a3");
+
+        let (b, s) = position("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        let q = Move::new(Square::A7, Square::B8, MoveFlag::Promotion, Queen);
+        assert_eq!(b.to_san(&s, q), "axb8=Q+");
+
+        let (b, s) = position("rnbqkbnr/ppppp2p/5p2/6p1/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3");
+        assert_eq!(b.to_san(&s, move_new!("d1h5")), "Qh5#");
+
+        for fen in [Board::STARTPOS, Board::KIWIPETE] {
+            let (b, s) = position(fen);
+            for &m in crate::movegen::generate_legal(&b, &s).iter() {
+                assert_eq!(b.parse_san(&s, &b.to_san(&s, m)), Some(m), "{m}");
+            }
+        }
+    }
+}
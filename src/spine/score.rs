@@ -0,0 +1,110 @@
+use std::fmt;
+use std::ops;
+
+/// An evaluation of a position in centipawns, from the point of view of the
+/// side to move. Scores beyond [`Score::MATE_BOUND`] are forced mates.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Score(i32);
+
+impl Score {
+    /// Neither side is better
+    pub const DRAW: Self = Self(0);
+    /// The side to move has been checkmated
+    pub const MATED: Self = Self(-32000);
+    /// The side to move can give mate on the spot
+    pub const MATE: Self = Self(32000);
+    /// Any score at least this large (or at most its negation) is a mate
+    pub const MATE_BOUND: Self = Self(32000 - 1000);
+    /// A win that is certain, though the distance to mate is not known
+    pub const KNOWN_WIN: Self = Self(10000);
+
+    /// A score of `cp` centipawns
+    pub const fn new(cp: i32) -> Self {
+        Self(cp)
+    }
+
+    /// The raw value in centipawns
+    pub const fn value(self) -> i32 {
+        self.0
+    }
+
+    /// The side to move mates in `ply` half moves
+    pub const fn mate_in(ply: usize) -> Self {
+        Self(Self::MATE.0 - ply as i32)
+    }
+
+    /// The side to move is mated in `ply` half moves
+    pub const fn mated_in(ply: usize) -> Self {
+        Self(Self::MATED.0 + ply as i32)
+    }
+
+    /// Whether the score is a forced mate for either side
+    pub const fn is_mate(self) -> bool {
+        self.0.abs() >= Self::MATE_BOUND.0
+    }
+
+    /// The number of half moves until mate, negative if the side to move is
+    /// the one being mated
+    pub const fn mate_plies(self) -> Option<i32> {
+        if !self.is_mate() {
+            None
+        } else if self.0 > 0 {
+            Some(Self::MATE.0 - self.0)
+        } else {
+            Some(Self::MATED.0 - self.0)
+        }
+    }
+}
+
+impl ops::Neg for Score {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl ops::Add<i32> for Score {
+    type Output = Self;
+    fn add(self, rhs: i32) -> Self {
+        Self(self.0 + rhs)
+    }
+}
+
+impl ops::Sub<i32> for Score {
+    type Output = Self;
+    fn sub(self, rhs: i32) -> Self {
+        Self(self.0 - rhs)
+    }
+}
+
+/// Written the way UCI reports scores: `cp 35`, or `mate 3` and `mate -2` in
+/// full moves
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mate_plies() {
+            Some(p) if p > 0 => write!(f, "mate {}", (p + 1) / 2),
+            Some(p) => write!(f, "mate {}", p / 2),
+            None => write!(f, "cp {}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mates() {
+        assert!(Score::mate_in(5).is_mate());
+        assert!(!Score::KNOWN_WIN.is_mate());
+        assert_eq!(Score::mate_in(5).mate_plies(), Some(5));
+        assert_eq!(Score::mated_in(4).mate_plies(), Some(-4));
+        assert_eq!(-Score::mate_in(3), Score::mated_in(3));
+
+        assert_eq!(Score::mate_in(1).to_string(), "mate 1");
+        assert_eq!(Score::mate_in(5).to_string(), "mate 3");
+        assert_eq!(Score::mated_in(4).to_string(), "mate -2");
+        assert_eq!(Score::new(-35).to_string(), "cp -35");
+    }
+}
@@ -0,0 +1,255 @@
+//! Deciding how long to think about a move.
+//!
+//! A [`TimeManager`] turns the limits of a `go` command into two deadlines.
+//! The soft one is checked between iterations of the search, and is
+//! stretched while the best move keeps changing or the score is falling. The
+//! hard one is checked during the search and is never passed.
+//!
+//! Time comes from a [`Clock`], so tests can move time forward by hand.
+
+use crate::prelude::*;
+
+use std::time::{Duration, Instant};
+
+/// How many more moves a game is assumed to last when the time control
+/// doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Something that can say how much time has passed since it started
+pub trait Clock {
+    fn elapsed(&self) -> Duration;
+}
+
+/// A [`Clock`] on the real time
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch(Instant);
+
+impl Stopwatch {
+    /// Start counting from now
+    pub fn start() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Clock for Stopwatch {
+    fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// The time control for a single move, as given to `go`.
+/// Anything left as `None` does not limit the search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The time left on each side's clock, `wtime` and `btime`
+    pub time: [Option<Duration>; Color::COUNT],
+    /// The time each side gains per move, `winc` and `binc`
+    pub increment: [Duration; Color::COUNT],
+    /// The moves left until the next time control, if it isn't sudden death
+    pub moves_to_go: Option<u32>,
+    /// A fixed time for this move
+    pub move_time: Option<Duration>,
+}
+
+/// Tracks the time spent on one move against its deadlines
+#[derive(Debug)]
+pub struct TimeManager<C: Clock> {
+    clock: C,
+    soft: Duration,
+    hard: Duration,
+    instability: f64,
+    last_best: Option<Move>,
+    last_score: Option<Score>,
+    drop_factor: f64,
+}
+
+impl<C: Clock> TimeManager<C> {
+    /// Plan the time for a move by `us` under `limits`, keeping `overhead`
+    /// back for the GUI and the connection. When `legal_moves` is one there is
+    /// nothing to think about, so the soft deadline is immediate.
+    pub fn new(
+        clock: C,
+        limits: &Limits,
+        us: Color,
+        overhead: Duration,
+        legal_moves: usize,
+    ) -> Self {
+        let (mut soft, hard) = if let Some(fixed) = limits.move_time {
+            let fixed = fixed.saturating_sub(overhead);
+            (fixed, fixed)
+        } else if let Some(time) = limits.time[us.to_usize()] {
+            let available = time.saturating_sub(overhead);
+            let moves_to_go = limits
+                .moves_to_go
+                .unwrap_or(DEFAULT_MOVES_TO_GO)
+                .clamp(1, 50);
+            let increment = limits.increment[us.to_usize()];
+
+            let soft = (available / moves_to_go + increment * 3 / 4).min(available / 2);
+            (soft, (soft * 4).min(available * 4 / 5))
+        } else {
+            (Duration::MAX, Duration::MAX)
+        };
+
+        if legal_moves == 1 {
+            soft = Duration::ZERO;
+        }
+
+        Self {
+            clock,
+            soft,
+            hard,
+            instability: 0.0,
+            last_best: None,
+            last_score: None,
+            drop_factor: 1.0,
+        }
+    }
+
+    /// The time spent on the move so far
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed()
+    }
+
+    /// The time the search should aim to use, as stretched by
+    /// [`TimeManager::iteration_done`] so far
+    pub fn soft_limit(&self) -> Duration {
+        if self.soft == Duration::MAX {
+            return Duration::MAX;
+        }
+        let scale = (1.0 + self.instability) * self.drop_factor;
+        self.soft.mul_f64(scale).min(self.hard)
+    }
+
+    /// The time the search must not go past
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+
+    /// Whether the search has to stop right away
+    pub fn out_of_time(&self) -> bool {
+        self.elapsed() >= self.hard
+    }
+
+    /// Report the result of a finished iteration, and learn whether to start
+    /// another one.
+    ///
+    /// A best move that differs from the last iteration's, or a score more
+    /// than a third of a pawn below it, gives the search more time.
+    pub fn iteration_done(&mut self, best: Move, score: Score) -> bool {
+        self.instability *= 0.5;
+        if self.last_best.is_some_and(|last| last != best) {
+            self.instability += 1.0;
+        }
+
+        self.drop_factor = match self.last_score {
+            Some(last) if !last.is_mate() && !score.is_mate() => {
+                let drop = (last.value() - score.value()).clamp(0, 200);
+                if drop > 30 {
+                    1.0 + drop as f64 / 200.0
+                } else {
+                    1.0
+                }
+            }
+            _ => 1.0,
+        };
+
+        self.last_best = Some(best);
+        self.last_score = Some(score);
+
+        self.elapsed() < self.soft_limit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::move_new;
+    use std::cell::Cell;
+
+    use Color::*;
+
+    impl Clock for &Cell<Duration> {
+        fn elapsed(&self) -> Duration {
+            self.get()
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn sudden_death(time: u64, increment: u64) -> Limits {
+        Limits {
+            time: [Some(ms(time)), Some(ms(1))],
+            increment: [ms(increment), ms(increment)],
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn allocation() {
+        let clock = Cell::new(Duration::ZERO);
+
+        let tm = TimeManager::new(&clock, &sudden_death(60_050, 0), White, ms(50), 20);
+        assert_eq!(tm.soft_limit(), ms(2_000));
+        assert_eq!(tm.hard_limit(), ms(8_000));
+
+        let tm = TimeManager::new(&clock, &sudden_death(60_050, 1_000), White, ms(50), 20);
+        assert_eq!(tm.soft_limit(), ms(2_750));
+
+        let limits = Limits {
+            moves_to_go: Some(1),
+            ..sudden_death(10_000, 0)
+        };
+        let tm = TimeManager::new(&clock, &limits, White, ms(0), 20);
+        assert_eq!(tm.soft_limit(), ms(5_000));
+        assert_eq!(tm.hard_limit(), ms(8_000));
+
+        let limits = Limits {
+            move_time: Some(ms(1_000)),
+            ..Limits::default()
+        };
+        let tm = TimeManager::new(&clock, &limits, Black, ms(100), 20);
+        assert_eq!(tm.soft_limit(), ms(900));
+        assert_eq!(tm.hard_limit(), ms(900));
+
+        let tm = TimeManager::new(&clock, &Limits::default(), Black, ms(100), 20);
+        assert_eq!(tm.hard_limit(), Duration::MAX);
+
+        // Black's clock is the one that counts for black
+        let tm = TimeManager::new(&clock, &sudden_death(60_000, 0), Black, ms(50), 20);
+        assert_eq!(tm.soft_limit(), Duration::ZERO);
+    }
+
+    #[test]
+    fn deadlines() {
+        let clock = Cell::new(Duration::ZERO);
+        let limits = sudden_death(30_000, 0);
+        let mut tm = TimeManager::new(&clock, &limits, White, ms(0), 20);
+        assert_eq!(tm.soft_limit(), ms(1_000));
+
+        clock.set(ms(500));
+        assert!(tm.iteration_done(move_new!("e2e4"), Score::new(20)));
+        clock.set(ms(1_200));
+        assert!(!tm.iteration_done(move_new!("e2e4"), Score::new(25)));
+
+        // A new best move buys more time, a falling score more still
+        assert!(tm.iteration_done(move_new!("d2d4"), Score::new(25)));
+        assert_eq!(tm.soft_limit(), ms(2_000));
+        assert!(tm.iteration_done(move_new!("d2d4"), Score::new(-75)));
+        assert_eq!(tm.soft_limit(), ms(2_250));
+
+        assert!(!tm.out_of_time());
+        clock.set(tm.hard_limit());
+        assert!(tm.out_of_time());
+    }
+
+    #[test]
+    fn single_reply() {
+        let clock = Cell::new(Duration::ZERO);
+        let mut tm = TimeManager::new(&clock, &sudden_death(30_000, 0), White, ms(0), 1);
+        assert!(!tm.iteration_done(move_new!("e1d1"), Score::DRAW));
+        assert!(!tm.out_of_time());
+    }
+}
@@ -0,0 +1,819 @@
+//! Playing engines against each other, to tell whether a change made one
+//! stronger.
+//!
+//! Engines are run as UCI subprocesses through [`UciEngine`], or anything
+//! else that implements [`Player`]. [`play_game`] keeps the board and the
+//! clocks itself, so an engine that makes an illegal move, crashes or
+//! overstays its time loses. Results are collected in [`Stats`], which gives
+//! the Elo difference with its error margin, and [`Sprt`] decides when there
+//! are enough games to accept or reject a change.
+
+use crate::book::Random64;
+use crate::endgame;
+use crate::movegen;
+use crate::pgn::{Outcome, PgnError, PgnGame};
+use crate::prelude::*;
+use crate::timeman::Limits;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long an engine may take to answer anything but `go`
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long past its clock an engine is waited for before it is given up on.
+/// It has lost either way; this only stops a hung engine hanging the match.
+const GRACE: Duration = Duration::from_secs(5);
+
+/// How long an engine that ran out of time has to answer `stop` before it is
+/// killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The time each side gets for a game: `base` for every `moves` moves (or
+/// the whole game), plus `increment` after each move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub moves: Option<u32>,
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    /// Read a time control in seconds, written `[moves/]base[+increment]`,
+    /// such as `10+0.1` or `40/60`
+    pub fn parse(s: &str) -> Option<Self> {
+        let (moves, rest) = match s.split_once('/') {
+            Some((m, rest)) => (Some(m.parse().ok().filter(|&m| m > 0)?), rest),
+            None => (None, s),
+        };
+        let (base, increment) = rest.split_once('+').unwrap_or((rest, "0"));
+        let secs = |t: &str| {
+            t.parse::<f64>()
+                .ok()
+                .filter(|t| t.is_finite() && *t >= 0.0)
+                .map(Duration::from_secs_f64)
+        };
+
+        Some(Self {
+            moves,
+            base: secs(base)?,
+            increment: secs(increment)?,
+        })
+    }
+
+    /// The time control as written in a PGN `TimeControl` tag
+    pub fn to_pgn(&self) -> String {
+        let mut s = format!("{}", self.base.as_secs_f64());
+        if let Some(moves) = self.moves {
+            s = format!("{moves}/{s}");
+        }
+        if !self.increment.is_zero() {
+            s += &format!("+{}", self.increment.as_secs_f64());
+        }
+        s
+    }
+}
+
+/// Where a game starts: a position, and moves already played from it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Opening {
+    /// The starting position, or `None` for the standard one
+    pub fen: Option<String>,
+    pub moves: Vec<Move>,
+}
+
+impl Opening {
+    /// The position of an EPD line, which is its first four fields
+    pub fn from_epd(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+        if fields.len() < 4 {
+            return None;
+        }
+
+        let fen = fields.join(" ");
+        let mut state = State::new();
+        Board::new(fen.as_str(), &mut state).ok()?;
+        Some(Self {
+            fen: Some(fen),
+            moves: Vec::new(),
+        })
+    }
+
+    /// The moves of a game, from its `FEN` tag if there is one
+    pub fn from_pgn(game: &PgnGame) -> Result<Self, PgnError> {
+        let mut moves = Vec::with_capacity(game.moves.len());
+        game.replay(|_, _, mv| {
+            moves.push(mv);
+            true
+        })?;
+
+        Ok(Self {
+            fen: game.tag("FEN").map(str::to_string),
+            moves,
+        })
+    }
+
+    fn start(&self) -> (Board, State) {
+        let mut state = State::new();
+        let fen = self.fen.as_deref().unwrap_or(Board::STARTPOS);
+        let board = Board::new(fen, &mut state).expect("openings are checked when made");
+        (board, state)
+    }
+}
+
+/// Something that can play a game: a UCI engine, or a stand-in for one
+pub trait Player {
+    /// The name to put in the PGN
+    fn name(&self) -> &str;
+
+    /// Get ready for a game against a new opponent or from a new opening
+    fn new_game(&mut self) -> io::Result<()>;
+
+    /// Pick a move for `us`, in UCI notation, in the position after `moves`
+    /// from `fen` (or the standard position), with the time left in `limits`
+    fn go(
+        &mut self,
+        fen: Option<&str>,
+        moves: &[Move],
+        us: Color,
+        limits: &Limits,
+    ) -> io::Result<String>;
+}
+
+/// An engine that speaks UCI, running as a child process
+#[derive(Debug)]
+pub struct UciEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciEngine {
+    /// Start `command` with `args`, wait for it to finish the UCI handshake
+    /// and set each of the `options`, given as name and value
+    pub fn start(command: &str, args: &[String], options: &[(String, String)]) -> io::Result<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        // Reading has to happen on its own thread, so a silent engine can
+        // be timed out
+        let (tx, lines) = mpsc::channel();
+        let _ = thread::spawn(move || {
+            for line in stdout.lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            name: command.to_string(),
+            child,
+            stdin,
+            lines,
+        };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line(STARTUP_TIMEOUT)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+
+        for (name, value) in options {
+            engine.send(&format!("setoption name {name} value {value}"))?;
+        }
+        engine.sync()?;
+
+        Ok(engine)
+    }
+
+    /// Use `name` instead of the one the engine gave
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{line}")?;
+        self.stdin.flush()
+    }
+
+    fn read_line(&mut self, timeout: Duration) -> io::Result<String> {
+        self.lines.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => {
+                io::Error::new(io::ErrorKind::TimedOut, "engine timed out")
+            }
+            RecvTimeoutError::Disconnected => {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "engine exited")
+            }
+        })
+    }
+
+    /// Wait until the engine has dealt with everything sent so far
+    fn sync(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        while self.read_line(STARTUP_TIMEOUT)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Stop a search that ran out of time, and read its `bestmove` so it
+    /// can't be taken for the answer to the next `go`. An engine that doesn't
+    /// stop in time is killed.
+    fn stop(&mut self) {
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        let stopped = self.send("stop").is_ok()
+            && loop {
+                let left = deadline.saturating_duration_since(Instant::now());
+                match self.read_line(left) {
+                    Ok(line) if best_move(&line).is_some() => break true,
+                    Ok(_) => (),
+                    Err(_) => break false,
+                }
+            };
+
+        if !stopped {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+impl Player for UciEngine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.sync()
+    }
+
+    fn go(
+        &mut self,
+        fen: Option<&str>,
+        moves: &[Move],
+        us: Color,
+        limits: &Limits,
+    ) -> io::Result<String> {
+        let mut position = match fen {
+            Some(fen) => format!("position fen {fen}"),
+            None => "position startpos".to_string(),
+        };
+        if !moves.is_empty() {
+            position += " moves";
+            for mv in moves {
+                position += &format!(" {mv}");
+            }
+        }
+        self.send(&position)?;
+
+        let ms = |d: Duration| d.as_millis();
+        let [wtime, btime] = limits.time.map(|t| t.unwrap_or_default());
+        let [winc, binc] = limits.increment;
+        let mut go = format!(
+            "go wtime {} btime {} winc {} binc {}",
+            ms(wtime),
+            ms(btime),
+            ms(winc),
+            ms(binc)
+        );
+        if let Some(n) = limits.moves_to_go {
+            go += &format!(" movestogo {n}");
+        }
+        self.send(&go)?;
+
+        let clock = limits.time[us.to_usize()].unwrap_or_default();
+        let deadline = Instant::now() + clock + GRACE;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let line = match self.read_line(left) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    self.stop();
+                    return Err(e);
+                }
+                line => line?,
+            };
+            if let Some(mv) = best_move(&line) {
+                return Ok(mv);
+            }
+        }
+    }
+}
+
+/// The move of a `bestmove` line
+fn best_move(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("bestmove")?;
+    Some(
+        rest.split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    )
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A finished game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayedGame {
+    pub white: String,
+    pub black: String,
+    pub opening: Opening,
+    /// Every move of the game, the opening's included
+    pub moves: Vec<Move>,
+    pub outcome: Outcome,
+    /// Why the game ended, such as `checkmate` or `white lost on time`
+    pub termination: String,
+}
+
+impl PlayedGame {
+    /// The game as PGN text, moves in SAN
+    pub fn to_pgn(&self, round: usize, time_control: &TimeControl) -> String {
+        let mut pgn = String::new();
+        let mut tag = |name: &str, value: &str| {
+            pgn += &format!(
+                "[{name} \"{}\"]\n",
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            );
+        };
+        tag("Event", "Engine match");
+        tag("Site", "?");
+        tag("Round", &round.to_string());
+        tag("White", &self.white);
+        tag("Black", &self.black);
+        tag("Result", self.outcome.to_pgn());
+        if let Some(fen) = &self.opening.fen {
+            tag("SetUp", "1");
+            tag("FEN", fen);
+        }
+        tag("TimeControl", &time_control.to_pgn());
+        tag("Termination", &self.termination);
+        pgn.push('\n');
+
+        let (mut board, mut state) = self.opening.start();
+        // `ply` only holds the move number until the first move is made
+        let mut number = board.ply();
+        let mut line = String::new();
+        for (i, &mv) in self.moves.iter().enumerate() {
+            let mut text = String::new();
+            let white = board.to_move() == Color::White;
+            if white || i == 0 {
+                text += &format!("{number}{} ", if white { "." } else { "..." });
+            }
+            if !white {
+                number += 1;
+            }
+            text += &board.to_san(&state, mv);
+
+            if !line.is_empty() && line.len() + text.len() + 1 > 79 {
+                pgn += &line;
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &text;
+            board.do_move(&mut state, mv).unwrap();
+        }
+        for &mv in self.moves.iter().rev() {
+            board.undo_move(&mut state, mv);
+        }
+
+        if !line.is_empty() && line.len() + 8 > 79 {
+            pgn += &line;
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        pgn += &line;
+        pgn += self.outcome.to_pgn();
+        pgn += "\n\n";
+        pgn
+    }
+}
+
+/// Whether the game is over without either side having to move: mate,
+/// stalemate, the fifty-move rule, threefold repetition of the positions
+/// with `keys`, or material that can't win
+fn adjudicate(board: &Board, state: &State, keys: &[u64]) -> Option<(Outcome, String)> {
+    if movegen::generate_legal(board, state).len() == 0 {
+        return Some(if state.checkers().gtz() {
+            (Outcome::Win(!board.to_move()), "checkmate".to_string())
+        } else {
+            (Outcome::Draw, "stalemate".to_string())
+        });
+    }
+
+    let draw = |reason: &str| Some((Outcome::Draw, reason.to_string()));
+    if state.half_moves() >= 100 {
+        return draw("fifty-move rule");
+    }
+    if let Some(last) = keys.last() {
+        if keys.iter().filter(|&k| k == last).count() >= 3 {
+            return draw("threefold repetition");
+        }
+    }
+    if endgame::probe(board, state) == Some(Score::DRAW) {
+        return draw("drawn material");
+    }
+
+    None
+}
+
+/// Play a game between `white` and `black` from `opening`, each with a clock
+/// set to `time_control`
+pub fn play_game(
+    white: &mut dyn Player,
+    black: &mut dyn Player,
+    opening: &Opening,
+    time_control: &TimeControl,
+) -> PlayedGame {
+    let random = Random64::polyglot();
+    let (mut board, mut state) = opening.start();
+    let mut moves = Vec::new();
+    let mut keys = vec![random.key(&board, &state)];
+
+    for &mv in &opening.moves {
+        board.do_move(&mut state, mv).unwrap();
+        moves.push(mv);
+        keys.push(random.key(&board, &state));
+    }
+
+    let mut clocks = [time_control.base; Color::COUNT];
+    let mut moves_to_go = [time_control.moves; Color::COUNT];
+    let color_name = |c: Color| if c == Color::White { "white" } else { "black" };
+
+    let started = [white.new_game(), black.new_game()];
+    let (outcome, termination) = 'game: {
+        for (color, result) in [Color::White, Color::Black].into_iter().zip(started) {
+            if let Err(e) = result {
+                let reason = format!("{} failed to start: {e}", color_name(color));
+                break 'game (Outcome::Win(!color), reason);
+            }
+        }
+
+        loop {
+            if let Some(end) = adjudicate(&board, &state, &keys) {
+                break end;
+            }
+
+            let us = board.to_move();
+            let limits = Limits {
+                time: clocks.map(Some),
+                increment: [time_control.increment; Color::COUNT],
+                moves_to_go: moves_to_go[us.to_usize()],
+                move_time: None,
+            };
+            let player: &mut dyn Player = match us {
+                Color::White => &mut *white,
+                Color::Black => &mut *black,
+            };
+
+            let start = Instant::now();
+            let answer = player.go(opening.fen.as_deref(), &moves, us, &limits);
+            let elapsed = start.elapsed();
+
+            let lost = |reason: String| (Outcome::Win(!us), format!("{} {reason}", color_name(us)));
+            let text = match answer {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => break lost("lost on time".into()),
+                Err(e) => break lost(format!("disconnected: {e}")),
+            };
+
+            let clock = &mut clocks[us.to_usize()];
+            if elapsed > *clock {
+                break lost("lost on time".into());
+            }
+            *clock = *clock - elapsed + time_control.increment;
+            if let Some(left) = &mut moves_to_go[us.to_usize()] {
+                *left -= 1;
+                if *left == 0 {
+                    *left = time_control.moves.unwrap();
+                    *clock += time_control.base;
+                }
+            }
+
+            let legal = movegen::generate_legal(&board, &state);
+            let Some(&mv) = legal.iter().find(|m| m.to_string() == text) else {
+                break lost(format!("made an illegal move: {text}"));
+            };
+            board.do_move(&mut state, mv).unwrap();
+            moves.push(mv);
+            keys.push(random.key(&board, &state));
+        }
+    };
+
+    for &mv in moves.iter().rev() {
+        board.undo_move(&mut state, mv);
+    }
+
+    PlayedGame {
+        white: white.name().to_string(),
+        black: black.name().to_string(),
+        opening: opening.clone(),
+        moves,
+        outcome,
+        termination,
+    }
+}
+
+/// The results of one engine against another, from the first one's side
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Stats {
+    /// Count a game in which the first engine played `color`
+    pub fn add(&mut self, outcome: Outcome, color: Color) {
+        match outcome {
+            Outcome::Win(c) if c == color => self.wins += 1,
+            Outcome::Win(_) => self.losses += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The fraction of the points the first engine scored
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// The variance of the score of a single game
+    fn variance(&self) -> f64 {
+        let s = self.score();
+        let n = self.games() as f64;
+        (self.wins as f64 * (1.0 - s).powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2)
+            + self.losses as f64 * s.powi(2))
+            / n
+    }
+
+    /// The Elo difference the results point to, and the margin around it
+    /// that holds the true difference with 95% confidence. `None` until both
+    /// engines have scored something.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let s = self.score();
+        if self.games() == 0 || s <= 0.0 || s >= 1.0 {
+            return None;
+        }
+
+        let margin = 1.959_964 * (self.variance() / self.games() as f64).sqrt();
+        let high = (s + margin).min(1.0 - f64::EPSILON);
+        let low = (s - margin).max(f64::EPSILON);
+        Some((elo(s), (elo(high) - elo(low)) / 2.0))
+    }
+}
+
+/// The Elo difference at which one side is expected to score `score`
+fn elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+/// The score one side is expected to get when it is `elo` stronger
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// A sequential probability ratio test of whether the first engine is
+/// `elo1` stronger (H1) rather than `elo0` stronger (H0), wrongly accepting
+/// H1 with probability `alpha` and wrongly accepting H0 with probability
+/// `beta`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+/// The hypothesis an [`Sprt`] accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hypothesis {
+    H0,
+    H1,
+}
+
+impl Sprt {
+    /// The log-likelihood ratios at which H0 and H1 are accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The log-likelihood ratio of H1 against H0 given `stats`, using the
+    /// normal approximation to the distribution of the score
+    pub fn llr(&self, stats: &Stats) -> f64 {
+        if stats.games() == 0 {
+            return 0.0;
+        }
+        let variance = stats.variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (s1 - s0) * (2.0 * stats.score() - s0 - s1) * stats.games() as f64 / (2.0 * variance)
+    }
+
+    /// The hypothesis to accept, if the test has finished
+    pub fn verdict(&self, stats: &Stats) -> Option<Hypothesis> {
+        let (lower, upper) = self.bounds();
+        let llr = self.llr(stats);
+        if llr >= upper {
+            Some(Hypothesis::H1)
+        } else if llr <= lower {
+            Some(Hypothesis::H0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spine::bitboard::initialize_bitboards;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    /// Plays the moves it is given, then resigns by making an illegal move
+    struct Scripted {
+        name: &'static str,
+        moves: Vec<&'static str>,
+    }
+
+    impl Player for Scripted {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn new_game(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn go(
+            &mut self,
+            _: Option<&str>,
+            _: &[Move],
+            us: Color,
+            limits: &Limits,
+        ) -> io::Result<String> {
+            assert!(limits.time[us.to_usize()].is_some());
+            Ok(if self.moves.is_empty() {
+                "a1a1".to_string()
+            } else {
+                self.moves.remove(0).to_string()
+            })
+        }
+    }
+
+    fn scripted(name: &'static str, moves: &[&'static str]) -> Scripted {
+        Scripted {
+            name,
+            moves: moves.to_vec(),
+        }
+    }
+
+    #[test]
+    fn time_controls() {
+        let tc = TimeControl::parse("40/60+0.5").unwrap();
+        assert_eq!(tc.moves, Some(40));
+        assert_eq!(tc.base, Duration::from_secs(60));
+        assert_eq!(tc.increment, Duration::from_millis(500));
+        assert_eq!(tc.to_pgn(), "40/60+0.5");
+
+        let tc = TimeControl::parse("10").unwrap();
+        assert_eq!((tc.moves, tc.increment), (None, Duration::ZERO));
+        assert_eq!(tc.to_pgn(), "10");
+
+        assert_eq!(TimeControl::parse("0/10"), None);
+        assert_eq!(TimeControl::parse("ten"), None);
+        assert_eq!(TimeControl::parse("10+-1"), None);
+    }
+
+    #[test]
+    fn games() {
+        INIT.call_once(initialize_bitboards);
+        let tc = TimeControl::parse("60+1").unwrap();
+
+        let opening = Opening {
+            fen: None,
+            moves: vec![crate::move_new!("f2f3")],
+        };
+        let mut white = scripted("W", &["g2g4"]);
+        let mut black = scripted("B", &["e7e5", "d8h4"]);
+        let game = play_game(&mut white, &mut black, &opening, &tc);
+        assert_eq!(game.outcome, Outcome::Win(Color::Black));
+        assert_eq!(game.termination, "checkmate");
+        assert_eq!(game.moves.len(), 4);
+
+        let pgn = game.to_pgn(1, &tc);
+        assert!(pgn.contains("[White \"W\"]\n"));
+        assert!(pgn.ends_with("\n\n1. f3 e5 2. g4 Qh4# 0-1\n\n"), "{pgn}");
+
+        let opening = Opening::from_epd("8/8/8/4k3/8/8/4K3/4N3 w - - bm Nd3;").unwrap();
+        let mut white = scripted("W", &[]);
+        let mut black = scripted("B", &[]);
+        let game = play_game(&mut white, &mut black, &opening, &tc);
+        assert_eq!(game.outcome, Outcome::Draw);
+        assert_eq!(game.termination, "drawn material");
+
+        let mut white = scripted("W", &["e2e4", "e1e2"]);
+        let mut black = scripted("B", &["e7e5"]);
+        let game = play_game(&mut white, &mut black, &Opening::default(), &tc);
+        assert_eq!(game.outcome, Outcome::Win(Color::White));
+        assert_eq!(game.termination, "black made an illegal move: a1a1");
+        assert!(game.to_pgn(2, &tc).contains("1. e4 e5 2. Ke2 1-0"));
+    }
+
+    #[test]
+    fn elo() {
+        let stats = Stats {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        let (elo, margin) = stats.elo().unwrap();
+        assert!((elo - 147.19).abs() < 0.01, "{elo}");
+        assert!(margin > 50.0 && margin < 100.0, "{margin}");
+
+        let mut even = Stats::default();
+        assert_eq!(even.elo(), None);
+        even.add(Outcome::Win(Color::White), Color::White);
+        even.add(Outcome::Win(Color::White), Color::Black);
+        assert_eq!(even.elo().unwrap().0, 0.0);
+    }
+
+    #[test]
+    fn sprt() {
+        let sprt = Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 0.001 && (lower + 2.944).abs() < 0.001);
+
+        let even = Stats {
+            wins: 10,
+            draws: 10,
+            losses: 10,
+        };
+        assert_eq!(sprt.verdict(&even), None);
+
+        let better = Stats {
+            wins: 1300,
+            draws: 2000,
+            losses: 1100,
+        };
+        assert!(sprt.llr(&better) > upper);
+        assert_eq!(sprt.verdict(&better), Some(Hypothesis::H1));
+
+        let worse = Stats {
+            wins: 1100,
+            draws: 2000,
+            losses: 1300,
+        };
+        assert_eq!(sprt.verdict(&worse), Some(Hypothesis::H0));
+    }
+}
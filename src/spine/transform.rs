@@ -0,0 +1,85 @@
+use crate::prelude::*;
+
+impl Board {
+    /// Flip the board over the middle of the board, between the fourth and fifth
+    /// ranks, and swap the colors of every piece (and the side to move).
+    ///
+    /// The resulting position is the same as the original from the other side's
+    /// point of view, castling rights and all.
+    pub fn mirror_vertical(&self, state: &State) -> (Self, State) {
+        let flip = |s: Square| s.relative_to(Color::Black);
+        let flip_right = |r: Option<CastleRight>| {
+            r.map(|r| CastleRight {
+                king_from: flip(r.king_from),
+                king_to: flip(r.king_to),
+                rook_from: flip(r.rook_from),
+                rook_to: flip(r.rook_to),
+            })
+        };
+
+        let cr = state.castle_rights();
+        let rights = CastleRights {
+            white_short: flip_right(cr.black_short),
+            white_long: flip_right(cr.black_long),
+            black_short: flip_right(cr.white_short),
+            black_long: flip_right(cr.white_long),
+        };
+
+        self.transform(state, flip, true, rights)
+    }
+
+    /// Flip the board over the middle of the board, between the d and e files.
+    ///
+    /// Kings and rooks leave the squares castling works from, so all castling
+    /// rights are lost.
+    pub fn mirror_horizontal(&self, state: &State) -> (Self, State) {
+        let flip = |s: Square| Square::new(s.to_u8() ^ 7);
+        self.transform(state, flip, false, CastleRights::new())
+    }
+
+    /// Rotate the board by 180 degrees and swap the colors of every piece, the
+    /// same as [`Board::mirror_vertical`] followed by [`Board::mirror_horizontal`].
+    pub fn rotate_180(&self, state: &State) -> (Self, State) {
+        let rotate = |s: Square| Square::new(63 - s.to_u8());
+        self.transform(state, rotate, true, CastleRights::new())
+    }
+
+    fn transform<F>(
+        &self,
+        state: &State,
+        map: F,
+        swap_colors: bool,
+        rights: CastleRights,
+    ) -> (Self, State)
+    where
+        F: Fn(Square) -> Square,
+    {
+        let mut builder = BoardBuilder::from_board(self, state);
+        for s in self.all() {
+            builder.clear(s);
+        }
+
+        for s in self.all() {
+            let p = self.get_piece(s).unwrap();
+            let p = if swap_colors {
+                p.kind() + !p.color()
+            } else {
+                p
+            };
+            builder.put(map(s), p);
+        }
+
+        let to_move = if swap_colors {
+            !self.to_move()
+        } else {
+            self.to_move()
+        };
+
+        builder
+            .side_to_move(to_move)
+            .castle_rights(rights)
+            .en_passant(state.en_passant().map(map))
+            .build_lenient()
+            .expect("a transformed position should be as valid as the original")
+    }
+}
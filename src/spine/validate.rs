@@ -0,0 +1,248 @@
+use crate::prelude::*;
+
+use std::fmt;
+
+use PieceType::*;
+
+/// The ways in which a [`Board`] and [`State`] may describe a position that
+/// could never arise in a game of chess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionError {
+    /// The given side has no king on the board
+    MissingKing(Color),
+    /// The given side has more than one king on the board
+    TooManyKings(Color),
+    /// The given side has more than sixteen pieces
+    TooManyPieces(Color),
+    /// The given side has more than eight pawns
+    TooManyPawns(Color),
+    /// The given side has more promoted pieces than missing pawns
+    TooManyPromoted(Color),
+    /// A pawn is standing on the first or eighth rank
+    PawnOnBackRank(Square),
+    /// The side that is *not* to move is in check
+    OpponentInCheck,
+    /// The side to move is in check from more than two pieces
+    TooManyCheckers,
+    /// A castling right has no king or rook on its starting square
+    InvalidCastleRight(CastleRight),
+    /// The en passant square could not have come from a double pawn push
+    InvalidEnPassant(Square),
+}
+
+impl Board {
+    /// Check that the position is one that could be reached in a game of chess.
+    ///
+    /// This is run by [`Board::new`], while [`Board::new_lenient`] only runs the
+    /// subset of it the move generator relies upon.
+    pub fn validate(&self, state: &State) -> Result<(), PositionError> {
+        self.validate_kings()?;
+        self.validate_material()?;
+        self.validate_state(state)
+    }
+
+    /// Every side must have exactly one king, or [`Board::king`] is meaningless.
+    pub(crate) fn validate_kings(&self) -> Result<(), PositionError> {
+        for color in [Color::White, Color::Black] {
+            match self.spec(color, King).popcount() {
+                0 => return Err(PositionError::MissingKing(color)),
+                1 => (),
+                _ => return Err(PositionError::TooManyKings(color)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Piece counts that cannot come about through captures and promotions.
+    fn validate_material(&self) -> Result<(), PositionError> {
+        let back_ranks = Bitboard::from(Rank::One) | Rank::Eight;
+        if let Some(sq) = (self.piece_type(Pawn) & back_ranks).next() {
+            return Err(PositionError::PawnOnBackRank(sq));
+        }
+
+        for color in [Color::White, Color::Black] {
+            let count = |pt: PieceType| self.spec(color, pt).popcount();
+
+            if self.color(color).popcount() > 16 {
+                return Err(PositionError::TooManyPieces(color));
+            }
+            if count(Pawn) > 8 {
+                return Err(PositionError::TooManyPawns(color));
+            }
+
+            let promoted = count(Knight).saturating_sub(2)
+                + count(Bishop).saturating_sub(2)
+                + count(Rook).saturating_sub(2)
+                + count(Queen).saturating_sub(1);
+            if promoted > 8 - count(Pawn) {
+                return Err(PositionError::TooManyPromoted(color));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks, castling rights and en passant, all of which the move
+    /// generator takes at face value.
+    pub(crate) fn validate_state(&self, state: &State) -> Result<(), PositionError> {
+        let us = self.to_move();
+        let them = !us;
+
+        if (self.attacks_to(self.king(them)) & self.color(us)).gtz() {
+            return Err(PositionError::OpponentInCheck);
+        }
+        if state.checkers().popcount() > 2 {
+            return Err(PositionError::TooManyCheckers);
+        }
+
+        for color in [Color::White, Color::Black] {
+            for right in state
+                .castle_rights()
+                .rights_for(color)
+                .into_iter()
+                .flatten()
+            {
+                if self.get_piece(right.king_from) != Some(King + color)
+                    || self.get_piece(right.rook_from) != Some(Rook + color)
+                {
+                    return Err(PositionError::InvalidCastleRight(right));
+                }
+            }
+        }
+
+        if let Some(ep) = state.en_passant() {
+            // The pawn that just moved passed over `ep`, so it now stands
+            // behind it (from our view) and its starting square is empty.
+            let ok = ep.rank() == Rank::Six.relative_to(us)
+                && self.get_piece(ep).is_none()
+                && self.get_piece(ep + ShiftDir::Forward(us)).is_none()
+                && self.get_piece(ep + ShiftDir::Backward(us)) == Some(Pawn + them);
+            if !ok {
+                return Err(PositionError::InvalidEnPassant(ep));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingKing(c) => write!(f, "{c:?} has no king"),
+            Self::TooManyKings(c) => write!(f, "{c:?} has more than one king"),
+            Self::TooManyPieces(c) => write!(f, "{c:?} has more than sixteen pieces"),
+            Self::TooManyPawns(c) => write!(f, "{c:?} has more than eight pawns"),
+            Self::TooManyPromoted(c) => {
+                write!(f, "{c:?} has more promoted pieces than missing pawns")
+            }
+            Self::PawnOnBackRank(s) => write!(f, "pawn on back rank square {s}"),
+            Self::OpponentInCheck => write!(f, "the side not to move is in check"),
+            Self::TooManyCheckers => write!(f, "the side to move is in check more than twice"),
+            Self::InvalidCastleRight(r) => write!(
+                f,
+                "castling right {}{} has no matching king and rook",
+                r.king_from, r.king_to
+            ),
+            Self::InvalidEnPassant(s) => write!(f, "en passant square {s} is impossible"),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spine::bitboard::initialize_bitboards;
+    use crate::spine::board::BoardCreationError;
+    use std::sync::Once;
+
+    use PositionError::*;
+
+    static INIT: Once = Once::new();
+
+    fn check(fen: &str, strict: bool) -> Result<(), PositionError> {
+        INIT.call_once(initialize_bitboards);
+        let mut s = State::new();
+        let res = if strict {
+            Board::new(fen, &mut s)
+        } else {
+            Board::new_lenient(fen, &mut s)
+        };
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(BoardCreationError::InvalidPosition(e)) => Err(e),
+            Err(e) => panic!("{fen} failed to parse: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn known_positions_are_valid() {
+        assert_eq!(check(Board::STARTPOS, true), Ok(()));
+        assert_eq!(check(Board::KIWIPETE, true), Ok(()));
+        assert_eq!(check("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -", true), Ok(()));
+        assert_eq!(
+            check(
+                "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+                true
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn kings() {
+        assert_eq!(
+            check("8/8/8/8/8/8/8/4K3 w - - 0 1", false),
+            Err(MissingKing(Color::Black))
+        );
+        assert_eq!(
+            check("k7/8/8/8/8/8/8/4K2K w - - 0 1", false),
+            Err(TooManyKings(Color::White))
+        );
+    }
+
+    #[test]
+    fn material() {
+        let nine_queens = "6nk/6pp/8/8/8/P7/QQQQQQQQ/QK6 w - - 0 1";
+        assert_eq!(check(nine_queens, true), Err(TooManyPromoted(Color::White)));
+        assert_eq!(check(nine_queens, false), Ok(()));
+
+        let back_rank = "k6P/8/8/8/8/8/8/K7 w - - 0 1";
+        assert_eq!(check(back_rank, true), Err(PawnOnBackRank(Square::H8)));
+        assert_eq!(check(back_rank, false), Ok(()));
+    }
+
+    #[test]
+    fn checks() {
+        assert_eq!(
+            check("k7/8/8/8/8/8/8/R3K3 w - - 0 1", false),
+            Err(OpponentInCheck)
+        );
+        assert_eq!(check("k7/8/8/8/8/2b5/8/4K2r w - - 0 1", true), Ok(()));
+        assert_eq!(
+            check("k7/8/8/8/1b6/3n4/8/r3K3 w - - 0 1", false),
+            Err(TooManyCheckers)
+        );
+    }
+
+    #[test]
+    fn castling_and_en_passant() {
+        assert!(matches!(
+            check("r3k2r/8/8/8/8/8/8/R3K3 w KQkq - 0 1", false),
+            Err(InvalidCastleRight(r)) if r.rook_from == Square::H1
+        ));
+        assert_eq!(
+            check("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", false),
+            Err(InvalidEnPassant(Square::E6))
+        );
+        assert_eq!(
+            check("4k3/8/8/4p3/8/8/8/4K3 b - e6 0 1", false),
+            Err(InvalidEnPassant(Square::E6))
+        );
+        assert_eq!(check("4k3/8/8/4pP2/8/8/8/4K3 w - e6 0 2", true), Ok(()));
+    }
+}